@@ -9,6 +9,7 @@
 //! - `buy`: Buy tokens with CSPR
 //! - `sell`: Sell tokens for CSPR
 //! - `get_price`: Query current price
+//! - `get_buy_cost`: Query the cost of buying an amount of tokens
 //! - `get_balance`: Query user's token balance
 //! - `get_reserve`: Query CSPR reserve
 //! - `deposit_reserve`: Add initial CSPR liquidity
@@ -81,6 +82,7 @@ const EP_INITIALIZE: &str = "initialize";
 const EP_BUY: &str = "buy";
 const EP_SELL: &str = "sell";
 const EP_GET_PRICE: &str = "get_price";
+const EP_GET_BUY_COST: &str = "get_buy_cost";
 const EP_GET_BALANCE: &str = "get_balance";
 const EP_GET_RESERVE: &str = "get_reserve";
 const EP_GET_SUPPLY: &str = "get_supply";
@@ -366,6 +368,14 @@ pub extern "C" fn get_price() {
     runtime::ret(CLValue::from_t(price).unwrap_or_revert());
 }
 
+/// Get what `buy` would charge for `token_amount` tokens at the current supply
+#[no_mangle]
+pub extern "C" fn get_buy_cost() {
+    let token_amount: U512 = runtime::get_named_arg("token_amount");
+    let cost = calculate_buy_cost(token_amount);
+    runtime::ret(CLValue::from_t(cost).unwrap_or_revert());
+}
+
 /// Get user's token balance
 #[no_mangle]
 pub extern "C" fn get_balance() {
@@ -480,6 +490,15 @@ fn build_entry_points() -> EntryPoints {
         EntryPointType::Called,
     ));
 
+    // get_buy_cost - view function
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_BUY_COST,
+        vec![Parameter::new("token_amount", CLType::U512)],
+        CLType::U512,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
     // get_balance - view function
     entry_points.add_entry_point(EntryPoint::new(
         EP_GET_BALANCE,
@@ -7,8 +7,18 @@
 //! - `lock_cspr`: Lock CSPR for a buy order
 //! - `unlock_cspr`: Unlock CSPR (admin only, for trade execution)
 //! - `cancel_order`: Cancel an order and refund locked CSPR
+//! - `increase_lock`: Add CSPR to an existing lock
+//! - `reduce_lock`: Refund part of an existing lock
+//! - `expire_lock`: Refund a lock past its expiry (anyone can call)
 //! - `set_order_book`: Set the authorized order book contract
-//! - `get_locked_amount`: Query locked amount for an order
+//! - `get_locked_amount`: Query locked amount for an `(owner, order_id)` pair
+//! - `get_lock`: Query the full lock record for an `(owner, order_id)` pair
+//! - `get_open_locks`: List an owner's open order IDs
+//! - `reconcile`: Query purse balance, total locked and surplus
+//! - `sweep_surplus`: Withdraw CSPR not backing any lock (admin only)
+//! - `pause` / `unpause`: Stop or resume locking and unlocking (admin only)
+//! - `trigger_emergency_exit`: Enter emergency exit mode after a long pause
+//! - `emergency_withdraw`: Withdraw all of the caller's locks in emergency exit mode
 
 #![no_std]
 #![no_main]
@@ -18,16 +28,20 @@ compile_error!("target arch should be wasm32: compile with '--target wasm32-unkn
 
 extern crate alloc;
 
+use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
+use alloc::vec::Vec;
 use casper_contract::{
     contract_api::{runtime, storage, system},
     unwrap_or_revert::UnwrapOrRevert,
 };
 use casper_types::{
     account::AccountHash,
+    bytesrepr::{self, FromBytes, ToBytes},
     contracts::{EntryPoint, EntryPoints, NamedKeys},
-    ApiError, CLType, CLValue, EntryPointAccess, EntryPointType, Parameter, URef, U512,
+    ApiError, CLType, CLTyped, CLValue, EntryPointAccess, EntryPointType, Parameter, URef, U512,
 };
 
 // ============================================================================
@@ -38,12 +52,26 @@ use casper_types::{
 pub enum VaultError {
     InsufficientBalance = 1,
     OrderNotFound = 2,
+    // Reserved: locks are addressed by (owner, order_id), so another account's
+    // order ID is simply not found. Kept so later codes keep their values.
+    #[allow(dead_code)]
     NotOrderOwner = 3,
     NotAuthorized = 4,
     AlreadyLocked = 5,
     InvalidAmount = 6,
     TransferFailed = 7,
     MissingKey = 10,
+    ExceedsSurplus = 11,
+    InvalidOrderId = 12,
+    InvalidExpiry = 13,
+    LockNotActive = 14,
+    LockExpired = 15,
+    LockNotExpired = 16,
+    Paused = 17,
+    NotPaused = 18,
+    EmergencyExitActive = 19,
+    EmergencyExitNotReady = 20,
+    EmergencyExitNotActive = 21,
 }
 
 impl From<VaultError> for ApiError {
@@ -52,6 +80,110 @@ impl From<VaultError> for ApiError {
     }
 }
 
+// ============================================================================
+// Lock Record
+// ============================================================================
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LockStatus {
+    Open = 0,
+    PartiallySettled = 1,
+    Settled = 2,
+    Cancelled = 3,
+    Expired = 4,
+}
+
+impl LockStatus {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LockStatus::Open),
+            1 => Some(LockStatus::PartiallySettled),
+            2 => Some(LockStatus::Settled),
+            3 => Some(LockStatus::Cancelled),
+            4 => Some(LockStatus::Expired),
+            _ => None,
+        }
+    }
+
+    /// Open and partially settled locks still hold CSPR
+    fn is_active(self) -> bool {
+        matches!(self, LockStatus::Open | LockStatus::PartiallySettled)
+    }
+}
+
+/// A single CSPR lock held by the vault
+pub struct LockRecord {
+    pub owner: AccountHash,
+    /// Total CSPR deposited into the lock, including top-ups
+    pub original_amount: U512,
+    pub remaining_amount: U512,
+    pub status: LockStatus,
+    pub created_at: u64,
+    pub updated_at: u64,
+    /// Block time (ms) after which the lock can be expired, 0 = never
+    pub expires_at: u64,
+}
+
+impl LockRecord {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+}
+
+impl CLTyped for LockRecord {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for LockRecord {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = bytesrepr::allocate_buffer(self)?;
+        buffer.extend(self.owner.to_bytes()?);
+        buffer.extend(self.original_amount.to_bytes()?);
+        buffer.extend(self.remaining_amount.to_bytes()?);
+        buffer.extend((self.status as u8).to_bytes()?);
+        buffer.extend(self.created_at.to_bytes()?);
+        buffer.extend(self.updated_at.to_bytes()?);
+        buffer.extend(self.expires_at.to_bytes()?);
+        Ok(buffer)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.owner.serialized_length()
+            + self.original_amount.serialized_length()
+            + self.remaining_amount.serialized_length()
+            + (self.status as u8).serialized_length()
+            + self.created_at.serialized_length()
+            + self.updated_at.serialized_length()
+            + self.expires_at.serialized_length()
+    }
+}
+
+impl FromBytes for LockRecord {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (owner, remainder) = AccountHash::from_bytes(bytes)?;
+        let (original_amount, remainder) = U512::from_bytes(remainder)?;
+        let (remaining_amount, remainder) = U512::from_bytes(remainder)?;
+        let (status, remainder) = u8::from_bytes(remainder)?;
+        let status = LockStatus::from_u8(status).ok_or(bytesrepr::Error::Formatting)?;
+        let (created_at, remainder) = u64::from_bytes(remainder)?;
+        let (updated_at, remainder) = u64::from_bytes(remainder)?;
+        let (expires_at, remainder) = u64::from_bytes(remainder)?;
+        let record = LockRecord {
+            owner,
+            original_amount,
+            remaining_amount,
+            status,
+            created_at,
+            updated_at,
+            expires_at,
+        };
+        Ok((record, remainder))
+    }
+}
+
 // ============================================================================
 // Constants
 // ============================================================================
@@ -62,15 +194,36 @@ const CONTRACT_PACKAGE_KEY: &str = "token_vault_package";
 const KEY_ADMIN: &str = "admin";
 const KEY_ORDER_BOOK: &str = "order_book";
 const KEY_CSPR_PURSE: &str = "cspr_purse";
-const DICT_LOCKED_CSPR: &str = "locked_cspr";
-const DICT_ORDER_OWNERS: &str = "order_owners";
+const KEY_TOTAL_LOCKED: &str = "total_locked";
+const KEY_PAUSED: &str = "paused";
+const KEY_PAUSED_AT: &str = "paused_at";
+const KEY_EMERGENCY_EXIT: &str = "emergency_exit";
+const DICT_LOCKS: &str = "locks";
+const DICT_OWNER_LOCKS: &str = "owner_locks";
+
+// Order IDs share the dictionary key with the owner hash (64 hex chars + separator)
+const MAX_ORDER_ID_LENGTH: usize = 32;
+
+// How long the vault must stay paused before emergency exit can be triggered
+const EMERGENCY_EXIT_DELAY_MS: u64 = 7 * 24 * 60 * 60 * 1000; // 7 days
 
 // Entry point names
 const EP_LOCK_CSPR: &str = "lock_cspr";
 const EP_UNLOCK_CSPR: &str = "unlock_cspr";
 const EP_CANCEL_ORDER: &str = "cancel_order";
+const EP_EXPIRE_LOCK: &str = "expire_lock";
+const EP_INCREASE_LOCK: &str = "increase_lock";
+const EP_REDUCE_LOCK: &str = "reduce_lock";
 const EP_SET_ORDER_BOOK: &str = "set_order_book";
 const EP_GET_LOCKED_AMOUNT: &str = "get_locked_amount";
+const EP_GET_LOCK: &str = "get_lock";
+const EP_GET_OPEN_LOCKS: &str = "get_open_locks";
+const EP_RECONCILE: &str = "reconcile";
+const EP_SWEEP_SURPLUS: &str = "sweep_surplus";
+const EP_PAUSE: &str = "pause";
+const EP_UNPAUSE: &str = "unpause";
+const EP_TRIGGER_EMERGENCY_EXIT: &str = "trigger_emergency_exit";
+const EP_EMERGENCY_WITHDRAW: &str = "emergency_withdraw";
 
 // ============================================================================
 // Helper Functions
@@ -119,29 +272,150 @@ fn only_order_book_or_admin() {
     runtime::revert(VaultError::NotAuthorized);
 }
 
+fn is_paused() -> bool {
+    let paused_uref = get_uref(KEY_PAUSED);
+    storage::read::<bool>(paused_uref)
+        .unwrap_or_revert()
+        .unwrap_or(false)
+}
+
+fn is_emergency_exit() -> bool {
+    let emergency_uref = get_uref(KEY_EMERGENCY_EXIT);
+    storage::read::<bool>(emergency_uref)
+        .unwrap_or_revert()
+        .unwrap_or(false)
+}
+
+fn when_not_paused() {
+    if is_paused() {
+        runtime::revert(VaultError::Paused);
+    }
+}
+
+/// Dictionary key for a lock, namespaced by its owner
+fn lock_key(owner: AccountHash, order_id: &str) -> String {
+    if order_id.is_empty() || order_id.len() > MAX_ORDER_ID_LENGTH {
+        runtime::revert(VaultError::InvalidOrderId);
+    }
+    format!("{}_{}", owner, order_id)
+}
+
+fn get_block_time() -> u64 {
+    runtime::get_blocktime().into()
+}
+
+fn read_lock(key: &str) -> Option<LockRecord> {
+    let locks_uref = get_uref(DICT_LOCKS);
+    storage::dictionary_get(locks_uref, key).unwrap_or_revert()
+}
+
+fn write_lock(key: &str, record: LockRecord) {
+    let locks_uref = get_uref(DICT_LOCKS);
+    storage::dictionary_put(locks_uref, key, record);
+}
+
+/// Read a lock that still holds CSPR
+fn read_active_lock(key: &str) -> LockRecord {
+    let record = read_lock(key).unwrap_or_revert_with(VaultError::OrderNotFound);
+    if !record.status.is_active() {
+        runtime::revert(VaultError::LockNotActive);
+    }
+    record
+}
+
+fn get_open_locks_of(owner: AccountHash) -> Vec<String> {
+    let owner_locks_uref = get_uref(DICT_OWNER_LOCKS);
+    storage::dictionary_get(owner_locks_uref, &owner.to_string())
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+fn add_open_lock(owner: AccountHash, order_id: &str) {
+    let mut open_locks = get_open_locks_of(owner);
+    open_locks.push(order_id.to_string());
+    let owner_locks_uref = get_uref(DICT_OWNER_LOCKS);
+    storage::dictionary_put(owner_locks_uref, &owner.to_string(), open_locks);
+}
+
+fn remove_open_lock(owner: AccountHash, order_id: &str) {
+    let mut open_locks = get_open_locks_of(owner);
+    open_locks.retain(|id| id != order_id);
+    let owner_locks_uref = get_uref(DICT_OWNER_LOCKS);
+    storage::dictionary_put(owner_locks_uref, &owner.to_string(), open_locks);
+}
+
+fn get_total_locked() -> U512 {
+    let total_uref = get_uref(KEY_TOTAL_LOCKED);
+    storage::read(total_uref)
+        .unwrap_or_revert()
+        .unwrap_or(U512::zero())
+}
+
+fn set_total_locked(value: U512) {
+    let total_uref = get_uref(KEY_TOTAL_LOCKED);
+    storage::write(total_uref, value);
+}
+
+/// Returns (purse balance, total locked, surplus)
+/// Surplus is whatever the purse holds beyond the sum of all locks
+fn reconcile_balances() -> (U512, U512, U512) {
+    let vault_purse = get_uref(KEY_CSPR_PURSE);
+    let balance = system::get_purse_balance(vault_purse)
+        .unwrap_or_revert_with(VaultError::MissingKey);
+    let total_locked = get_total_locked();
+    let surplus = balance.saturating_sub(total_locked);
+    (balance, total_locked, surplus)
+}
+
+/// Refund the remaining amount to the owner and move the lock to a terminal status
+fn close_lock(key: &str, order_id: &str, mut record: LockRecord, status: LockStatus) {
+    let refund = record.remaining_amount;
+    if refund > U512::zero() {
+        let vault_purse = get_uref(KEY_CSPR_PURSE);
+        system::transfer_from_purse_to_account(vault_purse, record.owner, refund, None)
+            .unwrap_or_revert_with(VaultError::TransferFailed);
+        set_total_locked(get_total_locked() - refund);
+    }
+
+    let owner = record.owner;
+    record.remaining_amount = U512::zero();
+    record.status = status;
+    record.updated_at = get_block_time();
+    write_lock(key, record);
+
+    remove_open_lock(owner, order_id);
+}
+
 // ============================================================================
 // Entry Points Implementation
 // ============================================================================
 
 /// Lock CSPR for a buy order
-/// Anyone can call this to lock their CSPR
+/// Anyone can call this to lock their CSPR under their own order ID namespace
 #[no_mangle]
 pub extern "C" fn lock_cspr() {
     let caller = runtime::get_caller();
     let order_id: String = runtime::get_named_arg("order_id");
     let amount: U512 = runtime::get_named_arg("amount");
+    let expires_at: u64 = runtime::get_named_arg("expires_at");
+
+    when_not_paused();
 
     if amount == U512::zero() {
         runtime::revert(VaultError::InvalidAmount);
     }
 
-    // Check if order already exists
-    let locked_cspr_uref = get_uref(DICT_LOCKED_CSPR);
-    let existing: Option<U512> = storage::dictionary_get(locked_cspr_uref, &order_id)
-        .unwrap_or_revert();
+    let now = get_block_time();
+    if expires_at != 0 && expires_at <= now {
+        runtime::revert(VaultError::InvalidExpiry);
+    }
 
-    if existing.is_some() {
-        runtime::revert(VaultError::AlreadyLocked);
+    // Order IDs of settled, cancelled or expired locks can be reused
+    let key = lock_key(caller, &order_id);
+    if let Some(existing) = read_lock(&key) {
+        if existing.status.is_active() {
+            runtime::revert(VaultError::AlreadyLocked);
+        }
     }
 
     // Get payment purse from caller and transfer to vault purse
@@ -151,12 +425,21 @@ pub extern "C" fn lock_cspr() {
     system::transfer_from_purse_to_purse(source_purse, vault_purse, amount, None)
         .unwrap_or_revert_with(VaultError::TransferFailed);
 
-    // Store locked amount
-    storage::dictionary_put(locked_cspr_uref, &order_id, amount);
-
-    // Store order owner
-    let order_owners_uref = get_uref(DICT_ORDER_OWNERS);
-    storage::dictionary_put(order_owners_uref, &order_id, caller);
+    // Store lock record
+    let record = LockRecord {
+        owner: caller,
+        original_amount: amount,
+        remaining_amount: amount,
+        status: LockStatus::Open,
+        created_at: now,
+        updated_at: now,
+        expires_at,
+    };
+    write_lock(&key, record);
+    set_total_locked(get_total_locked() + amount);
+
+    // Index the lock under its owner
+    add_open_lock(caller, &order_id);
 }
 
 /// Unlock CSPR and send to recipient (for trade execution)
@@ -164,19 +447,22 @@ pub extern "C" fn lock_cspr() {
 #[no_mangle]
 pub extern "C" fn unlock_cspr() {
     only_order_book_or_admin();
+    when_not_paused();
 
+    let owner: AccountHash = runtime::get_named_arg("owner");
     let order_id: String = runtime::get_named_arg("order_id");
     let recipient: AccountHash = runtime::get_named_arg("recipient");
     let amount: U512 = runtime::get_named_arg("amount");
 
-    let locked_cspr_uref = get_uref(DICT_LOCKED_CSPR);
+    let key = lock_key(owner, &order_id);
+    let mut record = read_active_lock(&key);
 
-    // Get locked amount
-    let locked: U512 = storage::dictionary_get(locked_cspr_uref, &order_id)
-        .unwrap_or_revert()
-        .unwrap_or_revert_with(VaultError::OrderNotFound);
+    let now = get_block_time();
+    if record.is_expired(now) {
+        runtime::revert(VaultError::LockExpired);
+    }
 
-    if locked < amount {
+    if record.remaining_amount < amount {
         runtime::revert(VaultError::InsufficientBalance);
     }
 
@@ -185,42 +471,186 @@ pub extern "C" fn unlock_cspr() {
     system::transfer_from_purse_to_account(vault_purse, recipient, amount, None)
         .unwrap_or_revert_with(VaultError::TransferFailed);
 
-    // Update or remove locked amount
-    let remaining = locked - amount;
-    storage::dictionary_put(locked_cspr_uref, &order_id, remaining);
+    // Update remaining amount and status
+    record.remaining_amount -= amount;
+    record.updated_at = now;
+    record.status = if record.remaining_amount == U512::zero() {
+        LockStatus::Settled
+    } else {
+        LockStatus::PartiallySettled
+    };
+    let settled = record.status == LockStatus::Settled;
+    write_lock(&key, record);
+    set_total_locked(get_total_locked() - amount);
+
+    if settled {
+        remove_open_lock(owner, &order_id);
+    }
 }
 
 /// Cancel an order and refund locked CSPR
-/// Only the order owner can cancel their own order
+/// Only the order owner can cancel their own order (also while paused)
 #[no_mangle]
 pub extern "C" fn cancel_order() {
     let caller = runtime::get_caller();
     let order_id: String = runtime::get_named_arg("order_id");
 
-    // Check order owner
-    let order_owners_uref = get_uref(DICT_ORDER_OWNERS);
-    let owner: AccountHash = storage::dictionary_get(order_owners_uref, &order_id)
-        .unwrap_or_revert()
-        .unwrap_or_revert_with(VaultError::OrderNotFound);
+    // Locks are keyed by owner, so callers can only ever reach their own
+    let key = lock_key(caller, &order_id);
+    let record = read_active_lock(&key);
+
+    close_lock(&key, &order_id, record, LockStatus::Cancelled);
+}
+
+/// Add CSPR from a payment purse to an existing lock
+/// Only the order owner can top up their own order
+#[no_mangle]
+pub extern "C" fn increase_lock() {
+    let caller = runtime::get_caller();
+    let order_id: String = runtime::get_named_arg("order_id");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    when_not_paused();
 
-    if owner != caller {
-        runtime::revert(VaultError::NotOrderOwner);
+    if amount == U512::zero() {
+        runtime::revert(VaultError::InvalidAmount);
+    }
+
+    let key = lock_key(caller, &order_id);
+    let mut record = read_active_lock(&key);
+
+    let now = get_block_time();
+    if record.is_expired(now) {
+        runtime::revert(VaultError::LockExpired);
     }
 
-    // Get locked amount
-    let locked_cspr_uref = get_uref(DICT_LOCKED_CSPR);
-    let locked: U512 = storage::dictionary_get(locked_cspr_uref, &order_id)
+    let source_purse: URef = runtime::get_named_arg("payment_purse");
+    let vault_purse = get_uref(KEY_CSPR_PURSE);
+
+    system::transfer_from_purse_to_purse(source_purse, vault_purse, amount, None)
+        .unwrap_or_revert_with(VaultError::TransferFailed);
+
+    record.original_amount += amount;
+    record.remaining_amount += amount;
+    record.updated_at = now;
+    write_lock(&key, record);
+    set_total_locked(get_total_locked() + amount);
+}
+
+/// Refund part of the remaining amount of a lock
+/// Only the order owner can reduce their own order (also while paused)
+#[no_mangle]
+pub extern "C" fn reduce_lock() {
+    let caller = runtime::get_caller();
+    let order_id: String = runtime::get_named_arg("order_id");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    if amount == U512::zero() {
+        runtime::revert(VaultError::InvalidAmount);
+    }
+
+    let key = lock_key(caller, &order_id);
+    let mut record = read_active_lock(&key);
+
+    if record.remaining_amount < amount {
+        runtime::revert(VaultError::InsufficientBalance);
+    }
+
+    // Reducing by the full remaining amount is a cancel
+    if record.remaining_amount == amount {
+        close_lock(&key, &order_id, record, LockStatus::Cancelled);
+        return;
+    }
+
+    let vault_purse = get_uref(KEY_CSPR_PURSE);
+    system::transfer_from_purse_to_account(vault_purse, caller, amount, None)
+        .unwrap_or_revert_with(VaultError::TransferFailed);
+
+    record.remaining_amount -= amount;
+    record.updated_at = get_block_time();
+    write_lock(&key, record);
+    set_total_locked(get_total_locked() - amount);
+}
+
+/// Expire a lock past its expiry and refund the owner
+/// Anyone can call this once the expiry has passed
+#[no_mangle]
+pub extern "C" fn expire_lock() {
+    let owner: AccountHash = runtime::get_named_arg("owner");
+    let order_id: String = runtime::get_named_arg("order_id");
+
+    let key = lock_key(owner, &order_id);
+    let record = read_active_lock(&key);
+
+    if !record.is_expired(get_block_time()) {
+        runtime::revert(VaultError::LockNotExpired);
+    }
+
+    close_lock(&key, &order_id, record, LockStatus::Expired);
+}
+
+/// Pause locking and unlocking
+/// Only callable by admin, owners can still cancel while paused
+#[no_mangle]
+pub extern "C" fn pause() {
+    only_admin();
+
+    if is_paused() {
+        return;
+    }
+
+    storage::write(get_uref(KEY_PAUSED), true);
+    storage::write(get_uref(KEY_PAUSED_AT), get_block_time());
+}
+
+/// Resume locking and unlocking
+/// Only callable by admin, impossible once emergency exit is active
+#[no_mangle]
+pub extern "C" fn unpause() {
+    only_admin();
+
+    if is_emergency_exit() {
+        runtime::revert(VaultError::EmergencyExitActive);
+    }
+
+    storage::write(get_uref(KEY_PAUSED), false);
+}
+
+/// Switch the vault into emergency exit mode
+/// Anyone can call this once the vault has been paused for EMERGENCY_EXIT_DELAY_MS
+#[no_mangle]
+pub extern "C" fn trigger_emergency_exit() {
+    if !is_paused() {
+        runtime::revert(VaultError::NotPaused);
+    }
+
+    let paused_at: u64 = storage::read(get_uref(KEY_PAUSED_AT))
         .unwrap_or_revert()
-        .unwrap_or(U512::zero());
+        .unwrap_or(0u64);
 
-    if locked > U512::zero() {
-        // Refund CSPR
-        let vault_purse = get_uref(KEY_CSPR_PURSE);
-        system::transfer_from_purse_to_account(vault_purse, caller, locked, None)
-            .unwrap_or_revert_with(VaultError::TransferFailed);
+    if get_block_time() < paused_at.saturating_add(EMERGENCY_EXIT_DELAY_MS) {
+        runtime::revert(VaultError::EmergencyExitNotReady);
+    }
 
-        // Clear locked amount
-        storage::dictionary_put(locked_cspr_uref, &order_id, U512::zero());
+    storage::write(get_uref(KEY_EMERGENCY_EXIT), true);
+}
+
+/// Withdraw the remaining balance of every open lock owned by the caller
+/// Only available in emergency exit mode
+#[no_mangle]
+pub extern "C" fn emergency_withdraw() {
+    if !is_emergency_exit() {
+        runtime::revert(VaultError::EmergencyExitNotActive);
+    }
+
+    let caller = runtime::get_caller();
+    for order_id in get_open_locks_of(caller) {
+        let key = lock_key(caller, &order_id);
+        if let Some(record) = read_lock(&key) {
+            if record.status.is_active() {
+                close_lock(&key, &order_id, record, LockStatus::Cancelled);
+            }
+        }
     }
 }
 
@@ -235,19 +665,71 @@ pub extern "C" fn set_order_book() {
     storage::write(order_book_uref, order_book);
 }
 
-/// Get locked CSPR for an order
+/// Get locked CSPR for an owner's order
 #[no_mangle]
 pub extern "C" fn get_locked_amount() {
+    let owner: AccountHash = runtime::get_named_arg("owner");
     let order_id: String = runtime::get_named_arg("order_id");
 
-    let locked_cspr_uref = get_uref(DICT_LOCKED_CSPR);
-    let locked: U512 = storage::dictionary_get(locked_cspr_uref, &order_id)
-        .unwrap_or_revert()
+    let key = lock_key(owner, &order_id);
+    let locked = read_lock(&key)
+        .map(|record| record.remaining_amount)
         .unwrap_or(U512::zero());
 
     runtime::ret(CLValue::from_t(locked).unwrap_or_revert());
 }
 
+/// Get the full lock record for an owner's order
+#[no_mangle]
+pub extern "C" fn get_lock() {
+    let owner: AccountHash = runtime::get_named_arg("owner");
+    let order_id: String = runtime::get_named_arg("order_id");
+
+    let key = lock_key(owner, &order_id);
+    let record = read_lock(&key).unwrap_or_revert_with(VaultError::OrderNotFound);
+
+    runtime::ret(CLValue::from_t(record).unwrap_or_revert());
+}
+
+/// Get the order IDs of an owner's open locks
+#[no_mangle]
+pub extern "C" fn get_open_locks() {
+    let owner: AccountHash = runtime::get_named_arg("owner");
+    let open_locks = get_open_locks_of(owner);
+    runtime::ret(CLValue::from_t(open_locks).unwrap_or_revert());
+}
+
+/// Compare the purse balance against the sum of all locks
+/// Returns (purse_balance, total_locked, surplus)
+#[no_mangle]
+pub extern "C" fn reconcile() {
+    let result = reconcile_balances();
+    runtime::ret(CLValue::from_t(result).unwrap_or_revert());
+}
+
+/// Withdraw surplus CSPR that does not back any lock
+/// Only callable by admin, can never touch locked funds
+#[no_mangle]
+pub extern "C" fn sweep_surplus() {
+    only_admin();
+
+    let recipient: AccountHash = runtime::get_named_arg("recipient");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    if amount == U512::zero() {
+        runtime::revert(VaultError::InvalidAmount);
+    }
+
+    let (_balance, _total_locked, surplus) = reconcile_balances();
+    if amount > surplus {
+        runtime::revert(VaultError::ExceedsSurplus);
+    }
+
+    let vault_purse = get_uref(KEY_CSPR_PURSE);
+    system::transfer_from_purse_to_account(vault_purse, recipient, amount, None)
+        .unwrap_or_revert_with(VaultError::TransferFailed);
+}
+
 // ============================================================================
 // Contract Installation
 // ============================================================================
@@ -262,6 +744,7 @@ fn build_entry_points() -> EntryPoints {
             Parameter::new("order_id", CLType::String),
             Parameter::new("amount", CLType::U512),
             Parameter::new("payment_purse", CLType::URef),
+            Parameter::new("expires_at", CLType::U64),
         ],
         CLType::Unit,
         EntryPointAccess::Public,
@@ -272,6 +755,7 @@ fn build_entry_points() -> EntryPoints {
     entry_points.add_entry_point(EntryPoint::new(
         EP_UNLOCK_CSPR,
         vec![
+            Parameter::new("owner", CLType::ByteArray(32)),
             Parameter::new("order_id", CLType::String),
             Parameter::new("recipient", CLType::ByteArray(32)),
             Parameter::new("amount", CLType::U512),
@@ -290,6 +774,43 @@ fn build_entry_points() -> EntryPoints {
         EntryPointType::Called,
     ));
 
+    // increase_lock - owner only (checked in code)
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_INCREASE_LOCK,
+        vec![
+            Parameter::new("order_id", CLType::String),
+            Parameter::new("amount", CLType::U512),
+            Parameter::new("payment_purse", CLType::URef),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // reduce_lock - owner only (checked in code)
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_REDUCE_LOCK,
+        vec![
+            Parameter::new("order_id", CLType::String),
+            Parameter::new("amount", CLType::U512),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // expire_lock - anyone can call (expiry checked in code)
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_EXPIRE_LOCK,
+        vec![
+            Parameter::new("owner", CLType::ByteArray(32)),
+            Parameter::new("order_id", CLType::String),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
     // set_order_book - admin only
     entry_points.add_entry_point(EntryPoint::new(
         EP_SET_ORDER_BOOK,
@@ -299,15 +820,100 @@ fn build_entry_points() -> EntryPoints {
         EntryPointType::Called,
     ));
 
+    // pause - admin only
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_PAUSE,
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // unpause - admin only
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_UNPAUSE,
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // trigger_emergency_exit - anyone can call (pause duration checked in code)
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_TRIGGER_EMERGENCY_EXIT,
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // emergency_withdraw - anyone can call, refunds only the caller's locks
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_EMERGENCY_WITHDRAW,
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
     // get_locked_amount - anyone can call
     entry_points.add_entry_point(EntryPoint::new(
         EP_GET_LOCKED_AMOUNT,
-        vec![Parameter::new("order_id", CLType::String)],
+        vec![
+            Parameter::new("owner", CLType::ByteArray(32)),
+            Parameter::new("order_id", CLType::String),
+        ],
         CLType::U512,
         EntryPointAccess::Public,
         EntryPointType::Called,
     ));
 
+    // get_lock - anyone can call
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_LOCK,
+        vec![
+            Parameter::new("owner", CLType::ByteArray(32)),
+            Parameter::new("order_id", CLType::String),
+        ],
+        CLType::Any,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // get_open_locks - anyone can call
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_OPEN_LOCKS,
+        vec![Parameter::new("owner", CLType::ByteArray(32))],
+        CLType::List(Box::new(CLType::String)),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // reconcile - anyone can call
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_RECONCILE,
+        vec![],
+        CLType::Tuple3([
+            Box::new(CLType::U512),
+            Box::new(CLType::U512),
+            Box::new(CLType::U512),
+        ]),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    // sweep_surplus - admin only
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_SWEEP_SURPLUS,
+        vec![
+            Parameter::new("recipient", CLType::ByteArray(32)),
+            Parameter::new("amount", CLType::U512),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
     entry_points
 }
 
@@ -319,8 +925,8 @@ pub extern "C" fn call() {
     let cspr_purse = system::create_purse();
 
     // Create dictionaries
-    let locked_cspr_uref = storage::new_dictionary(DICT_LOCKED_CSPR).unwrap_or_revert();
-    let order_owners_uref = storage::new_dictionary(DICT_ORDER_OWNERS).unwrap_or_revert();
+    let locks_uref = storage::new_dictionary(DICT_LOCKS).unwrap_or_revert();
+    let owner_locks_uref = storage::new_dictionary(DICT_OWNER_LOCKS).unwrap_or_revert();
 
     // Store admin
     let admin_uref = storage::new_uref(admin);
@@ -328,13 +934,25 @@ pub extern "C" fn call() {
     // Store order book (initially default)
     let order_book_uref = storage::new_uref(AccountHash::default());
 
+    // Running total of all locked CSPR
+    let total_locked_uref = storage::new_uref(U512::zero());
+
+    // Pause state
+    let paused_uref = storage::new_uref(false);
+    let paused_at_uref = storage::new_uref(0u64);
+    let emergency_exit_uref = storage::new_uref(false);
+
     // Build named keys for contract
     let mut named_keys = NamedKeys::new();
     named_keys.insert(KEY_ADMIN.to_string(), admin_uref.into());
     named_keys.insert(KEY_ORDER_BOOK.to_string(), order_book_uref.into());
     named_keys.insert(KEY_CSPR_PURSE.to_string(), cspr_purse.into());
-    named_keys.insert(DICT_LOCKED_CSPR.to_string(), locked_cspr_uref.into());
-    named_keys.insert(DICT_ORDER_OWNERS.to_string(), order_owners_uref.into());
+    named_keys.insert(KEY_TOTAL_LOCKED.to_string(), total_locked_uref.into());
+    named_keys.insert(KEY_PAUSED.to_string(), paused_uref.into());
+    named_keys.insert(KEY_PAUSED_AT.to_string(), paused_at_uref.into());
+    named_keys.insert(KEY_EMERGENCY_EXIT.to_string(), emergency_exit_uref.into());
+    named_keys.insert(DICT_LOCKS.to_string(), locks_uref.into());
+    named_keys.insert(DICT_OWNER_LOCKS.to_string(), owner_locks_uref.into());
 
     // Create entry points
     let entry_points = build_entry_points();
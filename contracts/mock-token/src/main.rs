@@ -0,0 +1,303 @@
+//! Mock CEP-18 Token for the Order Book Tests
+//!
+//! Keeps balances and allowances like CEP-18 does: keyed by the holder's key, where
+//! an account is its account hash and a calling contract is its package hash. Anyone
+//! can mint, so tests fund traders without an installer-held supply.
+//!
+//! # Entry Points
+//! - `mint`: Credit tokens to an owner
+//! - `approve`: Let a spender move the caller's tokens
+//! - `transfer`: Move the caller's tokens
+//! - `transfer_from`: Move an owner's tokens within the caller's allowance
+//! - `balance_of`: Get a holder's balance
+//! - `allowance`: Get a spender's allowance from an owner
+//! - `decimals`: Get the token's decimals
+
+#![no_std]
+#![no_main]
+
+#[cfg(not(target_arch = "wasm32"))]
+compile_error!("target arch should be wasm32: compile with '--target wasm32-unknown-unknown'");
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use base64ct::{Base64, Encoding};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    bytesrepr::ToBytes,
+    contracts::{EntryPoint, EntryPoints, NamedKeys},
+    system::Caller,
+    ApiError, CLType, CLValue, EntryPointAccess, EntryPointType, Key, Parameter, URef, U256,
+};
+
+/// Same codes as the CEP-18 reference contract
+#[repr(u16)]
+enum MockTokenError {
+    InsufficientBalance = 60001,
+    InsufficientAllowance = 60002,
+}
+
+impl From<MockTokenError> for ApiError {
+    fn from(error: MockTokenError) -> Self {
+        ApiError::User(error as u16)
+    }
+}
+
+const CONTRACT_NAME: &str = "mock_token";
+const CONTRACT_HASH_KEY: &str = "mock_token_hash";
+const CONTRACT_PACKAGE_KEY: &str = "mock_token_package";
+
+const KEY_DECIMALS: &str = "decimals";
+const DICT_BALANCES: &str = "balances";
+const DICT_ALLOWANCES: &str = "allowances";
+
+const EP_MINT: &str = "mint";
+const EP_APPROVE: &str = "approve";
+const EP_TRANSFER: &str = "transfer";
+const EP_TRANSFER_FROM: &str = "transfer_from";
+const EP_BALANCE_OF: &str = "balance_of";
+const EP_ALLOWANCE: &str = "allowance";
+const EP_DECIMALS: &str = "decimals";
+
+fn get_uref(name: &str) -> URef {
+    runtime::get_key(name)
+        .unwrap_or_revert()
+        .into_uref()
+        .unwrap_or_revert()
+}
+
+/// The account or contract package that called this entry point
+fn immediate_caller() -> Key {
+    match runtime::get_call_stack().iter().rev().nth(1) {
+        Some(Caller::Initiator { account_hash }) => Key::Account(*account_hash),
+        Some(Caller::SmartContract {
+            contract_package_hash,
+            ..
+        }) => Key::Hash(contract_package_hash.value()),
+        Some(Caller::Entity { package_hash, .. }) => Key::Hash(package_hash.value()),
+        _ => runtime::revert(ApiError::InvalidCallerInfoRequest),
+    }
+}
+
+/// Base64 of the holder's key bytes, as CEP-18 names balance entries
+fn balance_item_key(owner: Key) -> String {
+    let bytes = owner.to_bytes().unwrap_or_revert();
+    Base64::encode_string(&bytes)
+}
+
+/// Hex of the hashed owner and spender keys, as CEP-18 names allowance entries
+fn allowance_item_key(owner: Key, spender: Key) -> String {
+    let mut preimage: Vec<u8> = owner.to_bytes().unwrap_or_revert();
+    preimage.extend(spender.to_bytes().unwrap_or_revert());
+    runtime::blake2b(preimage)
+        .iter()
+        .map(|byte| alloc::format!("{:02x}", byte))
+        .collect()
+}
+
+fn read_balance(owner: Key) -> U256 {
+    storage::dictionary_get(get_uref(DICT_BALANCES), &balance_item_key(owner))
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+fn write_balance(owner: Key, balance: U256) {
+    storage::dictionary_put(get_uref(DICT_BALANCES), &balance_item_key(owner), balance);
+}
+
+fn read_allowance(owner: Key, spender: Key) -> U256 {
+    storage::dictionary_get(
+        get_uref(DICT_ALLOWANCES),
+        &allowance_item_key(owner, spender),
+    )
+    .unwrap_or_revert()
+    .unwrap_or_default()
+}
+
+fn write_allowance(owner: Key, spender: Key, amount: U256) {
+    storage::dictionary_put(
+        get_uref(DICT_ALLOWANCES),
+        &allowance_item_key(owner, spender),
+        amount,
+    );
+}
+
+fn move_tokens(owner: Key, recipient: Key, amount: U256) {
+    let owner_balance = read_balance(owner);
+    if owner_balance < amount {
+        runtime::revert(MockTokenError::InsufficientBalance);
+    }
+    write_balance(owner, owner_balance - amount);
+    write_balance(recipient, read_balance(recipient) + amount);
+}
+
+/// Credit `amount` new tokens to `owner`, open to anyone
+#[no_mangle]
+pub extern "C" fn mint() {
+    let owner: Key = runtime::get_named_arg("owner");
+    let amount: U256 = runtime::get_named_arg("amount");
+    write_balance(owner, read_balance(owner) + amount);
+}
+
+/// Set the caller's allowance for `spender`
+#[no_mangle]
+pub extern "C" fn approve() {
+    let spender: Key = runtime::get_named_arg("spender");
+    let amount: U256 = runtime::get_named_arg("amount");
+    write_allowance(immediate_caller(), spender, amount);
+}
+
+/// Move `amount` of the caller's tokens to `recipient`
+#[no_mangle]
+pub extern "C" fn transfer() {
+    let recipient: Key = runtime::get_named_arg("recipient");
+    let amount: U256 = runtime::get_named_arg("amount");
+    move_tokens(immediate_caller(), recipient, amount);
+}
+
+/// Move `amount` of `owner`'s tokens to `recipient`, spending the caller's allowance
+#[no_mangle]
+pub extern "C" fn transfer_from() {
+    let owner: Key = runtime::get_named_arg("owner");
+    let recipient: Key = runtime::get_named_arg("recipient");
+    let amount: U256 = runtime::get_named_arg("amount");
+
+    let spender = immediate_caller();
+    let allowance = read_allowance(owner, spender);
+    if allowance < amount {
+        runtime::revert(MockTokenError::InsufficientAllowance);
+    }
+    write_allowance(owner, spender, allowance - amount);
+    move_tokens(owner, recipient, amount);
+}
+
+/// Get the balance held by `address`
+#[no_mangle]
+pub extern "C" fn balance_of() {
+    let address: Key = runtime::get_named_arg("address");
+    runtime::ret(CLValue::from_t(read_balance(address)).unwrap_or_revert());
+}
+
+/// Get how much `spender` may still move of `owner`'s tokens
+#[no_mangle]
+pub extern "C" fn allowance() {
+    let owner: Key = runtime::get_named_arg("owner");
+    let spender: Key = runtime::get_named_arg("spender");
+    runtime::ret(CLValue::from_t(read_allowance(owner, spender)).unwrap_or_revert());
+}
+
+/// Get the decimals set at install
+#[no_mangle]
+pub extern "C" fn decimals() {
+    let decimals: u8 = storage::read(get_uref(KEY_DECIMALS))
+        .unwrap_or_revert()
+        .unwrap_or_revert();
+    runtime::ret(CLValue::from_t(decimals).unwrap_or_revert());
+}
+
+fn build_entry_points() -> EntryPoints {
+    let mut entry_points = EntryPoints::new();
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_MINT,
+        vec![
+            Parameter::new("owner", CLType::Key),
+            Parameter::new("amount", CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_APPROVE,
+        vec![
+            Parameter::new("spender", CLType::Key),
+            Parameter::new("amount", CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_TRANSFER,
+        vec![
+            Parameter::new("recipient", CLType::Key),
+            Parameter::new("amount", CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_TRANSFER_FROM,
+        vec![
+            Parameter::new("owner", CLType::Key),
+            Parameter::new("recipient", CLType::Key),
+            Parameter::new("amount", CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_BALANCE_OF,
+        vec![Parameter::new("address", CLType::Key)],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_ALLOWANCE,
+        vec![
+            Parameter::new("owner", CLType::Key),
+            Parameter::new("spender", CLType::Key),
+        ],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_DECIMALS,
+        vec![],
+        CLType::U8,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let decimals: u8 = runtime::get_named_arg("decimals");
+
+    let balances_uref = storage::new_dictionary(DICT_BALANCES).unwrap_or_revert();
+    let allowances_uref = storage::new_dictionary(DICT_ALLOWANCES).unwrap_or_revert();
+
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(KEY_DECIMALS.to_string(), storage::new_uref(decimals).into());
+    named_keys.insert(DICT_BALANCES.to_string(), balances_uref.into());
+    named_keys.insert(DICT_ALLOWANCES.to_string(), allowances_uref.into());
+
+    let (contract_hash, _contract_version) = storage::new_contract(
+        build_entry_points().into(),
+        Some(named_keys),
+        Some(CONTRACT_PACKAGE_KEY.to_string()),
+        Some(CONTRACT_NAME.to_string()),
+        None,
+    );
+
+    runtime::put_key(CONTRACT_HASH_KEY, contract_hash.into());
+}
@@ -3,13 +3,41 @@
 //! Implements a limit order book with price-time priority matching.
 //! Supports buy/sell limit orders with partial fills.
 //!
+//! One install hosts many markets, each trading a CEP-18 token against CSPR.
+//!
 //! # Entry Points
+//! - `create_market`: List a new token market (admin or launchpad only)
+//! - `set_market_params`: Set a market's tick size, lot size and minimum notional (admin only)
+//! - `set_launchpad`: Set the launchpad allowed to create markets (admin only)
+//! - `set_market_amm`: Link a market to its bonding curve for routing (admin or launchpad)
 //! - `place_buy_order`: Place a buy limit order (escrows CSPR)
 //! - `place_sell_order`: Place a sell limit order (escrows tokens)
+//! - `place_market_buy`: Buy at market up to a worst price and CSPR spend cap, optionally
+//!   routing the remainder to the bonding curve
+//! - `place_market_sell`: Sell at market down to a worst price
 //! - `cancel_order`: Cancel an open order
+//! - `cancel_all`: Cancel all of the caller's open orders in a market
+//! - `batch_orders`: Place and cancel several orders in one market atomically
+//! - `amend_order`: Change an open order's price and/or quantity
 //! - `get_order`: Get order details
 //! - `get_best_bid`: Get highest buy price
 //! - `get_best_ask`: Get lowest sell price
+//! - `get_depth`: Get aggregated open quantity per price level for one side
+//! - `purge_expired`: Refund and remove expired resting orders (anyone can call)
+//! - `get_user_orders`: Page through an account's orders, optionally by status
+//! - `get_trades`: Page through executed trades by trade ID
+//! - `place_conditional_buy` / `place_conditional_sell`: Place a stop or take-profit order
+//! - `cancel_conditional_order`: Cancel a conditional order before it triggers
+//! - `trigger_orders`: Activate conditional orders whose trigger was reached (keepers)
+//! - `get_conditional_order`: Get conditional order details
+//! - `set_self_trade_mode`: Choose how the caller's own orders are kept from matching
+//! - `set_keeper_bounty`: Set the bounty paid per triggered order (admin only)
+//! - `set_fee_schedule`: Set maker and taker fees in basis points (admin only)
+//! - `get_fee_schedule`: Get the maker and taker fees
+//! - `withdraw_fees`: Withdraw collected fees (admin only)
+//! - `deposit_tokens`: Deposit CEP-18 tokens for selling
+//! - `withdraw_tokens`: Withdraw CEP-18 tokens not locked in orders
+//! - `withdraw_cspr`: Withdraw CSPR credited from sales and refunds
 
 #![no_std]
 #![no_main]
@@ -19,6 +47,8 @@ compile_error!("target arch should be wasm32: compile with '--target wasm32-unkn
 
 extern crate alloc;
 
+use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
@@ -28,8 +58,10 @@ use casper_contract::{
 };
 use casper_types::{
     account::AccountHash,
-    contracts::{EntryPoint, EntryPoints, NamedKeys},
-    ApiError, CLType, CLValue, EntryPointAccess, EntryPointType, Parameter, URef, U512,
+    bytesrepr::{self, FromBytes, ToBytes},
+    contracts::{ContractHash, ContractPackageHash, EntryPoint, EntryPoints, NamedKeys},
+    runtime_args, ApiError, CLType, CLTyped, CLValue, EntryPointAccess, EntryPointType, Key,
+    Parameter, RuntimeArgs, URef, U256, U512,
 };
 
 // ============================================================================
@@ -47,6 +79,19 @@ pub enum OrderBookError {
     OrderAlreadyFilled = 7,
     MathOverflow = 8,
     MissingKey = 9,
+    OrderNotFilled = 10,
+    WouldTakeLiquidity = 11,
+    ConditionalNotPending = 12,
+    InvalidExpiry = 13,
+    InvalidFee = 14,
+    MarketNotFound = 15,
+    MarketExists = 16,
+    PriceOffTick = 17,
+    AmountOffLot = 18,
+    BelowMinNotional = 19,
+    InvalidDecimals = 20,
+    AmmNotSet = 21,
+    InvalidRoute = 22,
 }
 
 impl From<OrderBookError> for ApiError {
@@ -65,33 +110,479 @@ const CONTRACT_PACKAGE_KEY: &str = "orderbook_package";
 
 // Storage keys
 const KEY_ADMIN: &str = "admin";
-const KEY_CSPR_PURSE: &str = "cspr_escrow";
+const KEY_LAUNCHPAD: &str = "launchpad";
 const KEY_ORDER_COUNTER: &str = "order_counter";
+const KEY_MARKET_COUNTER: &str = "market_counter";
+const KEY_KEEPER_BOUNTY: &str = "keeper_bounty";
+const KEY_CONDITIONAL_COUNTER: &str = "conditional_counter";
+const KEY_SELF_PACKAGE: &str = "self_package";
+const KEY_TRADE_COUNTER: &str = "trade_counter";
+const KEY_MAKER_FEE_BPS: &str = "maker_fee_bps";
+const KEY_TAKER_FEE_BPS: &str = "taker_fee_bps";
+
+// Per-market keys, prefixed with the market ID
+// The escrow purse is a named key, the rest live in the market_state dictionary
+const KEY_CSPR_PURSE: &str = "cspr_escrow";
 const KEY_BEST_BID: &str = "best_bid";
 const KEY_BEST_ASK: &str = "best_ask";
+const KEY_BID_PRICES: &str = "bid_prices";
+const KEY_ASK_PRICES: &str = "ask_prices";
+const KEY_LAST_PRICE: &str = "last_price";
+const KEY_PENDING_CONDITIONALS: &str = "pending_conditionals";
+const KEY_EXPIRING_ORDERS: &str = "expiring_orders";
+const KEY_FEE_BALANCE: &str = "fee_balance";
+const KEY_TICK_SIZE: &str = "tick_size";
+const KEY_LOT_SIZE: &str = "lot_size";
+const KEY_MIN_NOTIONAL: &str = "min_notional";
+const KEY_TOKEN_DECIMALS: &str = "decimals";
+const KEY_AMM: &str = "bonding_curve_amm";
+
+const DICT_MARKETS: &str = "markets";
+const DICT_MARKET_STATE: &str = "market_state";
 const DICT_ORDERS: &str = "orders";
 const DICT_USER_ORDERS: &str = "user_orders";
+const DICT_USER_OPEN_ORDERS: &str = "user_open_orders";
 const DICT_TOKEN_BALANCES: &str = "token_balances";
+const DICT_CSPR_BALANCES: &str = "cspr_balances";
+const DICT_PRICE_LEVELS: &str = "price_levels";
+const DICT_CONDITIONAL_ORDERS: &str = "conditional_orders";
+const DICT_TRADES: &str = "trades";
+const DICT_SELF_TRADE_MODES: &str = "self_trade_modes";
 
 // Entry point names
+const EP_CREATE_MARKET: &str = "create_market";
+const EP_SET_LAUNCHPAD: &str = "set_launchpad";
+const EP_SET_MARKET_PARAMS: &str = "set_market_params";
+const EP_SET_MARKET_AMM: &str = "set_market_amm";
 const EP_PLACE_BUY_ORDER: &str = "place_buy_order";
 const EP_PLACE_SELL_ORDER: &str = "place_sell_order";
+const EP_PLACE_MARKET_BUY: &str = "place_market_buy";
+const EP_PLACE_MARKET_SELL: &str = "place_market_sell";
 const EP_CANCEL_ORDER: &str = "cancel_order";
+const EP_CANCEL_ALL: &str = "cancel_all";
+const EP_BATCH_ORDERS: &str = "batch_orders";
+const EP_AMEND_ORDER: &str = "amend_order";
 const EP_GET_ORDER: &str = "get_order";
 const EP_GET_BEST_BID: &str = "get_best_bid";
 const EP_GET_BEST_ASK: &str = "get_best_ask";
+const EP_GET_DEPTH: &str = "get_depth";
 const EP_DEPOSIT_TOKENS: &str = "deposit_tokens";
 const EP_WITHDRAW_TOKENS: &str = "withdraw_tokens";
+const EP_WITHDRAW_CSPR: &str = "withdraw_cspr";
+const EP_GET_USER_ORDERS: &str = "get_user_orders";
+const EP_PLACE_CONDITIONAL_BUY: &str = "place_conditional_buy";
+const EP_PLACE_CONDITIONAL_SELL: &str = "place_conditional_sell";
+const EP_CANCEL_CONDITIONAL_ORDER: &str = "cancel_conditional_order";
+const EP_TRIGGER_ORDERS: &str = "trigger_orders";
+const EP_GET_CONDITIONAL_ORDER: &str = "get_conditional_order";
+const EP_SET_KEEPER_BOUNTY: &str = "set_keeper_bounty";
+const EP_PURGE_EXPIRED: &str = "purge_expired";
+const EP_GET_TRADES: &str = "get_trades";
+const EP_SET_FEE_SCHEDULE: &str = "set_fee_schedule";
+const EP_GET_FEE_SCHEDULE: &str = "get_fee_schedule";
+const EP_WITHDRAW_FEES: &str = "withdraw_fees";
+const EP_SET_SELF_TRADE_MODE: &str = "set_self_trade_mode";
+
+// Prices are motes per whole token, amounts are in the token's base units
+const MAX_TOKEN_DECIMALS: u8 = 32;
+
+// CEP-18 entry point read when a market is created without explicit decimals
+const CEP18_DECIMALS: &str = "decimals";
+
+// Fees are in basis points of the trade value
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// Highest fee the admin can set, buy orders escrow this much on top of their cost
+const MAX_FEE_BPS: u32 = 100; // 1%
+
+// CEP-18 entry points used for custody
+const CEP18_TRANSFER: &str = "transfer";
+const CEP18_TRANSFER_FROM: &str = "transfer_from";
+
+// Bonding curve entry points used to route buys the book cannot fill
+const AMM_BUY: &str = "buy";
+const AMM_GET_BUY_COST: &str = "get_buy_cost";
+
+// Upper bound on the page size of get_user_orders and get_trades
+const MAX_PAGE_SIZE: u64 = 100;
+
+// Default bounty paid to the keeper per triggered conditional order
+const DEFAULT_KEEPER_BOUNTY: u64 = 100_000_000; // 0.1 CSPR
+
+// ============================================================================
+// Order
+// ============================================================================
+
+/// Implement `CLTyped`, `ToBytes` and `FromBytes` for a fieldless enum stored as a u8
+macro_rules! u8_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($variant:ident = $value:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(u8)]
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($variant = $value,)+
+        }
+
+        impl CLTyped for $name {
+            fn cl_type() -> CLType {
+                CLType::U8
+            }
+        }
+
+        impl ToBytes for $name {
+            fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+                (*self as u8).to_bytes()
+            }
+
+            fn serialized_length(&self) -> usize {
+                bytesrepr::U8_SERIALIZED_LENGTH
+            }
+        }
+
+        impl FromBytes for $name {
+            fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+                let (value, remainder) = u8::from_bytes(bytes)?;
+                let parsed = match value {
+                    $($value => $name::$variant,)+
+                    _ => return Err(bytesrepr::Error::Formatting),
+                };
+                Ok((parsed, remainder))
+            }
+        }
+    };
+}
+
+// Sides and statuses are stored and passed as a single u8
+u8_enum! {
+    pub enum OrderSide {
+        Buy = 0,
+        Sell = 1,
+    }
+}
+
+impl OrderSide {
+    fn opposite(self) -> Self {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
+u8_enum! {
+    pub enum OrderStatus {
+        Open = 0,
+        Filled = 1,
+        Cancelled = 2,
+        Partial = 3,
+        Expired = 4,
+    }
+}
+
+u8_enum! {
+    /// Time-in-force of a new order
+    pub enum OrderType {
+        Limit = 0,
+        ImmediateOrCancel = 1,
+        FillOrKill = 2,
+        PostOnly = 3,
+    }
+}
+
+u8_enum! {
+    /// What to cancel when an account's new order would match its own resting order
+    pub enum SelfTradeMode {
+        CancelNewest = 0,
+        CancelOldest = 1,
+        CancelBoth = 2,
+    }
+}
+
+/// A limit order as stored in the `orders` dictionary
+#[derive(Clone)]
+pub struct Order {
+    /// Token contract of the market the order trades in
+    pub market: ContractHash,
+    pub owner: AccountHash,
+    pub side: OrderSide,
+    pub price: U512,
+    pub amount: U512,
+    pub filled: U512,
+    /// Amount bought on the market's bonding curve instead of the book
+    pub routed: U512,
+    pub status: OrderStatus,
+    /// Block time (ms) the order was placed
+    pub created_at: u64,
+    /// Block time (ms) from which the order can no longer fill, 0 = never
+    pub expires_at: u64,
+}
+
+impl Order {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    fn remaining(&self) -> U512 {
+        self.amount - self.filled - self.routed
+    }
+
+    fn is_open(&self) -> bool {
+        matches!(self.status, OrderStatus::Open | OrderStatus::Partial)
+    }
+
+    /// Update status after a fill
+    fn refresh_status(&mut self) {
+        self.status = if self.remaining() == U512::zero() {
+            OrderStatus::Filled
+        } else if self.filled > U512::zero() {
+            OrderStatus::Partial
+        } else {
+            OrderStatus::Open
+        };
+    }
+}
+
+impl CLTyped for Order {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for Order {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = bytesrepr::allocate_buffer(self)?;
+        buffer.extend(self.market.to_bytes()?);
+        buffer.extend(self.owner.to_bytes()?);
+        buffer.extend(self.side.to_bytes()?);
+        buffer.extend(self.price.to_bytes()?);
+        buffer.extend(self.amount.to_bytes()?);
+        buffer.extend(self.filled.to_bytes()?);
+        buffer.extend(self.routed.to_bytes()?);
+        buffer.extend(self.status.to_bytes()?);
+        buffer.extend(self.created_at.to_bytes()?);
+        buffer.extend(self.expires_at.to_bytes()?);
+        Ok(buffer)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.market.serialized_length()
+            + self.owner.serialized_length()
+            + self.side.serialized_length()
+            + self.price.serialized_length()
+            + self.amount.serialized_length()
+            + self.filled.serialized_length()
+            + self.routed.serialized_length()
+            + self.status.serialized_length()
+            + self.created_at.serialized_length()
+            + self.expires_at.serialized_length()
+    }
+}
+
+impl FromBytes for Order {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (market, remainder) = ContractHash::from_bytes(bytes)?;
+        let (owner, remainder) = AccountHash::from_bytes(remainder)?;
+        let (side, remainder) = OrderSide::from_bytes(remainder)?;
+        let (price, remainder) = U512::from_bytes(remainder)?;
+        let (amount, remainder) = U512::from_bytes(remainder)?;
+        let (filled, remainder) = U512::from_bytes(remainder)?;
+        let (routed, remainder) = U512::from_bytes(remainder)?;
+        let (status, remainder) = OrderStatus::from_bytes(remainder)?;
+        let (created_at, remainder) = u64::from_bytes(remainder)?;
+        let (expires_at, remainder) = u64::from_bytes(remainder)?;
+        let order = Order {
+            market,
+            owner,
+            side,
+            price,
+            amount,
+            filled,
+            routed,
+            status,
+            created_at,
+            expires_at,
+        };
+        Ok((order, remainder))
+    }
+}
+
+/// A single fill as stored in the `trades` dictionary
+pub struct Trade {
+    pub market: ContractHash,
+    pub buy_order_id: u64,
+    pub sell_order_id: u64,
+    /// Execution price, the maker's limit price
+    pub price: U512,
+    pub amount: U512,
+    /// Side of the resting order that provided liquidity
+    pub maker_side: OrderSide,
+    /// Block time (ms) of the fill
+    pub timestamp: u64,
+}
+
+impl CLTyped for Trade {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for Trade {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = bytesrepr::allocate_buffer(self)?;
+        buffer.extend(self.market.to_bytes()?);
+        buffer.extend(self.buy_order_id.to_bytes()?);
+        buffer.extend(self.sell_order_id.to_bytes()?);
+        buffer.extend(self.price.to_bytes()?);
+        buffer.extend(self.amount.to_bytes()?);
+        buffer.extend(self.maker_side.to_bytes()?);
+        buffer.extend(self.timestamp.to_bytes()?);
+        Ok(buffer)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.market.serialized_length()
+            + self.buy_order_id.serialized_length()
+            + self.sell_order_id.serialized_length()
+            + self.price.serialized_length()
+            + self.amount.serialized_length()
+            + self.maker_side.serialized_length()
+            + self.timestamp.serialized_length()
+    }
+}
+
+impl FromBytes for Trade {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (market, remainder) = ContractHash::from_bytes(bytes)?;
+        let (buy_order_id, remainder) = u64::from_bytes(remainder)?;
+        let (sell_order_id, remainder) = u64::from_bytes(remainder)?;
+        let (price, remainder) = U512::from_bytes(remainder)?;
+        let (amount, remainder) = U512::from_bytes(remainder)?;
+        let (maker_side, remainder) = OrderSide::from_bytes(remainder)?;
+        let (timestamp, remainder) = u64::from_bytes(remainder)?;
+        let trade = Trade {
+            market,
+            buy_order_id,
+            sell_order_id,
+            price,
+            amount,
+            maker_side,
+            timestamp,
+        };
+        Ok((trade, remainder))
+    }
+}
+
+u8_enum! {
+    pub enum ConditionalStatus {
+        Pending = 0,
+        Triggered = 1,
+        Cancelled = 2,
+    }
+}
+
+/// A stop-loss or take-profit order waiting for its trigger
+/// Its escrow and keeper bounty are already held by the contract
+pub struct ConditionalOrder {
+    pub market: ContractHash,
+    pub owner: AccountHash,
+    pub side: OrderSide,
+    /// Last trade price that activates the order
+    pub trigger_price: U512,
+    /// Trigger when the last price is at or above `trigger_price`, otherwise at or below
+    pub trigger_above: bool,
+    /// Limit price of the order once live
+    pub price: U512,
+    pub amount: U512,
+    /// CSPR escrowed for the keeper who triggers the order
+    pub bounty: U512,
+    pub status: ConditionalStatus,
+    /// ID of the live order once triggered, 0 before
+    pub order_id: u64,
+    pub created_at: u64,
+}
+
+impl ConditionalOrder {
+    fn is_triggered_by(&self, last_price: U512) -> bool {
+        if self.trigger_above {
+            last_price >= self.trigger_price
+        } else {
+            last_price <= self.trigger_price
+        }
+    }
+}
+
+impl CLTyped for ConditionalOrder {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for ConditionalOrder {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = bytesrepr::allocate_buffer(self)?;
+        buffer.extend(self.market.to_bytes()?);
+        buffer.extend(self.owner.to_bytes()?);
+        buffer.extend(self.side.to_bytes()?);
+        buffer.extend(self.trigger_price.to_bytes()?);
+        buffer.extend(self.trigger_above.to_bytes()?);
+        buffer.extend(self.price.to_bytes()?);
+        buffer.extend(self.amount.to_bytes()?);
+        buffer.extend(self.bounty.to_bytes()?);
+        buffer.extend(self.status.to_bytes()?);
+        buffer.extend(self.order_id.to_bytes()?);
+        buffer.extend(self.created_at.to_bytes()?);
+        Ok(buffer)
+    }
 
-// Order sides
-const SIDE_BUY: u8 = 0;
-const SIDE_SELL: u8 = 1;
+    fn serialized_length(&self) -> usize {
+        self.market.serialized_length()
+            + self.owner.serialized_length()
+            + self.side.serialized_length()
+            + self.trigger_price.serialized_length()
+            + self.trigger_above.serialized_length()
+            + self.price.serialized_length()
+            + self.amount.serialized_length()
+            + self.bounty.serialized_length()
+            + self.status.serialized_length()
+            + self.order_id.serialized_length()
+            + self.created_at.serialized_length()
+    }
+}
 
-// Order status
-const STATUS_OPEN: u8 = 0;
-const STATUS_FILLED: u8 = 1;
-const STATUS_CANCELLED: u8 = 2;
-const STATUS_PARTIAL: u8 = 3;
+impl FromBytes for ConditionalOrder {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (market, remainder) = ContractHash::from_bytes(bytes)?;
+        let (owner, remainder) = AccountHash::from_bytes(remainder)?;
+        let (side, remainder) = OrderSide::from_bytes(remainder)?;
+        let (trigger_price, remainder) = U512::from_bytes(remainder)?;
+        let (trigger_above, remainder) = bool::from_bytes(remainder)?;
+        let (price, remainder) = U512::from_bytes(remainder)?;
+        let (amount, remainder) = U512::from_bytes(remainder)?;
+        let (bounty, remainder) = U512::from_bytes(remainder)?;
+        let (status, remainder) = ConditionalStatus::from_bytes(remainder)?;
+        let (order_id, remainder) = u64::from_bytes(remainder)?;
+        let (created_at, remainder) = u64::from_bytes(remainder)?;
+        let conditional = ConditionalOrder {
+            market,
+            owner,
+            side,
+            trigger_price,
+            trigger_above,
+            price,
+            amount,
+            bounty,
+            status,
+            order_id,
+            created_at,
+        };
+        Ok((conditional, remainder))
+    }
+}
 
 // ============================================================================
 // Helper Functions
@@ -111,6 +602,58 @@ fn get_admin() -> AccountHash {
         .unwrap_or_revert()
 }
 
+fn only_admin() {
+    let caller = runtime::get_caller();
+    let admin = get_admin();
+    if caller != admin {
+        runtime::revert(OrderBookError::NotAuthorized);
+    }
+}
+
+fn only_launchpad_or_admin() {
+    let caller = runtime::get_caller();
+    let admin = get_admin();
+    if caller == admin {
+        return;
+    }
+
+    if let Some(key) = runtime::get_key(KEY_LAUNCHPAD) {
+        let launchpad_uref = key.into_uref().unwrap_or_revert();
+        let launchpad: AccountHash = storage::read(launchpad_uref)
+            .unwrap_or_revert()
+            .unwrap_or(AccountHash::default());
+
+        if caller == launchpad && launchpad != AccountHash::default() {
+            return;
+        }
+    }
+
+    runtime::revert(OrderBookError::NotAuthorized);
+}
+
+/// Internal ID of the market trading `token`
+fn get_market_id(token: ContractHash) -> u64 {
+    let markets_uref = get_uref(DICT_MARKETS);
+    storage::dictionary_get(markets_uref, &token.to_string())
+        .unwrap_or_revert()
+        .unwrap_or_revert_with(OrderBookError::MarketNotFound)
+}
+
+// Per-market storage is keyed "<market_id>_<name>", market IDs keep keys short
+fn market_key(market_id: u64, name: &str) -> String {
+    format!("{}_{}", market_id, name)
+}
+
+fn read_market_value<T: CLTyped + FromBytes>(market_id: u64, name: &str) -> Option<T> {
+    let state_uref = get_uref(DICT_MARKET_STATE);
+    storage::dictionary_get(state_uref, &market_key(market_id, name)).unwrap_or_revert()
+}
+
+fn write_market_value<T: CLTyped + ToBytes>(market_id: u64, name: &str, value: T) {
+    let state_uref = get_uref(DICT_MARKET_STATE);
+    storage::dictionary_put(state_uref, &market_key(market_id, name), value);
+}
+
 fn get_order_counter() -> u64 {
     let counter_uref = get_uref(KEY_ORDER_COUNTER);
     storage::read(counter_uref)
@@ -123,292 +666,1903 @@ fn set_order_counter(value: u64) {
     storage::write(counter_uref, value);
 }
 
-fn get_best_bid() -> U512 {
-    let bid_uref = get_uref(KEY_BEST_BID);
-    storage::read(bid_uref)
-        .unwrap_or_revert()
-        .unwrap_or(U512::zero())
+fn read_best_bid(market_id: u64) -> U512 {
+    read_market_value(market_id, KEY_BEST_BID).unwrap_or(U512::zero())
 }
 
-fn set_best_bid(price: U512) {
-    let bid_uref = get_uref(KEY_BEST_BID);
-    storage::write(bid_uref, price);
+fn set_best_bid(market_id: u64, price: U512) {
+    write_market_value(market_id, KEY_BEST_BID, price);
 }
 
-fn get_best_ask() -> U512 {
-    let ask_uref = get_uref(KEY_BEST_ASK);
-    storage::read(ask_uref)
-        .unwrap_or_revert()
-        .unwrap_or(U512::MAX)
+fn read_best_ask(market_id: u64) -> U512 {
+    read_market_value(market_id, KEY_BEST_ASK).unwrap_or(U512::MAX)
+}
+
+fn set_best_ask(market_id: u64, price: U512) {
+    write_market_value(market_id, KEY_BEST_ASK, price);
+}
+
+fn get_last_price(market_id: u64) -> U512 {
+    read_market_value(market_id, KEY_LAST_PRICE).unwrap_or(U512::zero())
 }
 
-fn set_best_ask(price: U512) {
-    let ask_uref = get_uref(KEY_BEST_ASK);
-    storage::write(ask_uref, price);
+fn set_last_price(market_id: u64, price: U512) {
+    write_market_value(market_id, KEY_LAST_PRICE, price);
 }
 
-fn get_token_balance(account: AccountHash) -> U512 {
+fn get_keeper_bounty() -> U512 {
+    let bounty_uref = get_uref(KEY_KEEPER_BOUNTY);
+    storage::read(bounty_uref)
+        .unwrap_or_revert()
+        .unwrap_or(U512::from(DEFAULT_KEEPER_BOUNTY))
+}
+
+fn get_token_balance(market_id: u64, account: AccountHash) -> U512 {
     let balances_uref = get_uref(DICT_TOKEN_BALANCES);
-    let key = account.to_string();
+    let key = market_key(market_id, &account.to_string());
     storage::dictionary_get::<U512>(balances_uref, &key)
         .unwrap_or_revert()
         .unwrap_or(U512::zero())
 }
 
-fn set_token_balance(account: AccountHash, balance: U512) {
+fn set_token_balance(market_id: u64, account: AccountHash, balance: U512) {
     let balances_uref = get_uref(DICT_TOKEN_BALANCES);
-    let key = account.to_string();
+    let key = market_key(market_id, &account.to_string());
     storage::dictionary_put(balances_uref, &key, balance);
 }
 
-// Order structure stored as: (owner, side, price, amount, filled, status, timestamp)
-// Encoded as comma-separated string for simplicity
-fn encode_order(
-    owner: AccountHash,
-    side: u8,
-    price: U512,
-    amount: U512,
-    filled: U512,
-    status: u8,
-) -> String {
-    // Format: owner_hex,side,price,amount,filled,status
-    let mut s = String::new();
-    s.push_str(&owner.to_string());
-    s.push(',');
-    s.push_str(&side.to_string());
-    s.push(',');
-    s.push_str(&price.to_string());
-    s.push(',');
-    s.push_str(&amount.to_string());
-    s.push(',');
-    s.push_str(&filled.to_string());
-    s.push(',');
-    s.push_str(&status.to_string());
-    s
+// CSPR owed to an account in a market, held in the market's escrow purse
+fn get_cspr_balance(market_id: u64, account: AccountHash) -> U512 {
+    let balances_uref = get_uref(DICT_CSPR_BALANCES);
+    let key = market_key(market_id, &account.to_string());
+    storage::dictionary_get::<U512>(balances_uref, &key)
+        .unwrap_or_revert()
+        .unwrap_or(U512::zero())
 }
 
-// ============================================================================
-// Entry Points Implementation
-// ============================================================================
-
-/// Place a buy limit order
-/// Escrows CSPR from the payment_purse
-#[no_mangle]
-pub extern "C" fn place_buy_order() {
-    let caller = runtime::get_caller();
-    let price: U512 = runtime::get_named_arg("price"); // Price per token in motes
-    let amount: U512 = runtime::get_named_arg("amount"); // Token amount
-    let payment_purse: URef = runtime::get_named_arg("payment_purse");
+fn set_cspr_balance(market_id: u64, account: AccountHash, balance: U512) {
+    let balances_uref = get_uref(DICT_CSPR_BALANCES);
+    let key = market_key(market_id, &account.to_string());
+    storage::dictionary_put(balances_uref, &key, balance);
+}
 
-    if price == U512::zero() {
-        runtime::revert(OrderBookError::InvalidPrice);
-    }
+/// Credit CSPR to an account's balance instead of transferring it right away
+fn credit_cspr(market_id: u64, account: AccountHash, amount: U512) {
     if amount == U512::zero() {
-        runtime::revert(OrderBookError::InvalidAmount);
+        return;
     }
+    let balance = get_cspr_balance(market_id, account);
+    set_cspr_balance(market_id, account, balance + amount);
+}
 
-    // Calculate total cost
-    let total_cost = price * amount / U512::from(1_000_000_000u64); // Assuming 9 decimals
-
-    // Transfer CSPR to escrow
-    let escrow_purse = get_uref(KEY_CSPR_PURSE);
-    system::transfer_from_purse_to_purse(payment_purse, escrow_purse, total_cost, None)
-        .unwrap_or_revert_with(OrderBookError::TransferFailed);
-
-    // Create order
-    let order_id = get_order_counter() + 1;
-    set_order_counter(order_id);
-
-    let order_data = encode_order(caller, SIDE_BUY, price, amount, U512::zero(), STATUS_OPEN);
-
-    // Store order
-    let orders_uref = get_uref(DICT_ORDERS);
-    storage::dictionary_put(orders_uref, &order_id.to_string(), order_data);
-
-    // Update best bid if this is higher
-    let current_best_bid = get_best_bid();
-    if price > current_best_bid {
-        set_best_bid(price);
+/// Return CSPR to an order's owner: paid out if they are the caller, otherwise
+/// credited, so keepers and takers never push transfers to other accounts
+fn refund_cspr(market_id: u64, owner: AccountHash, amount: U512) {
+    if owner == runtime::get_caller() {
+        pay_cspr(market_id, owner, amount);
+    } else {
+        credit_cspr(market_id, owner, amount);
     }
+}
 
-    // Return order ID
-    runtime::ret(CLValue::from_t(order_id).unwrap_or_revert());
+// Per-account order history, one entry per order so placing never rewrites it:
+// "<account>" holds the number of entries and "<account>_<n>" the n-th order ID
+fn get_order_history_len(account: AccountHash) -> u64 {
+    let history_uref = get_uref(DICT_USER_ORDERS);
+    storage::dictionary_get(history_uref, &account.to_string())
+        .unwrap_or_revert()
+        .unwrap_or(0)
 }
 
-/// Place a sell limit order
-/// Requires tokens to be deposited first
-#[no_mangle]
-pub extern "C" fn place_sell_order() {
-    let caller = runtime::get_caller();
-    let price: U512 = runtime::get_named_arg("price"); // Price per token in motes
-    let amount: U512 = runtime::get_named_arg("amount"); // Token amount
+fn read_order_history(account: AccountHash, index: u64) -> u64 {
+    let history_uref = get_uref(DICT_USER_ORDERS);
+    storage::dictionary_get(history_uref, &format!("{}_{}", account, index))
+        .unwrap_or_revert()
+        .unwrap_or_revert_with(OrderBookError::MissingKey)
+}
 
-    if price == U512::zero() {
-        runtime::revert(OrderBookError::InvalidPrice);
-    }
-    if amount == U512::zero() {
-        runtime::revert(OrderBookError::InvalidAmount);
-    }
+fn append_order_history(account: AccountHash, order_id: u64) {
+    let history_uref = get_uref(DICT_USER_ORDERS);
+    let len = get_order_history_len(account);
+    storage::dictionary_put(history_uref, &format!("{}_{}", account, len), order_id);
+    storage::dictionary_put(history_uref, &account.to_string(), len + 1);
+}
 
-    // Check user has enough tokens
-    let user_balance = get_token_balance(caller);
-    if user_balance < amount {
-        runtime::revert(OrderBookError::InsufficientFunds);
-    }
+// Per-account index of the orders still resting in the book
+fn read_open_orders(account: AccountHash) -> Vec<u64> {
+    let index_uref = get_uref(DICT_USER_OPEN_ORDERS);
+    storage::dictionary_get(index_uref, &account.to_string())
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
 
-    // Lock tokens (reduce available balance)
-    set_token_balance(caller, user_balance - amount);
+fn write_open_orders(account: AccountHash, order_ids: Vec<u64>) {
+    let index_uref = get_uref(DICT_USER_OPEN_ORDERS);
+    storage::dictionary_put(index_uref, &account.to_string(), order_ids);
+}
 
-    // Create order
-    let order_id = get_order_counter() + 1;
-    set_order_counter(order_id);
+fn index_open_order(account: AccountHash, order_id: u64) {
+    let mut order_ids = read_open_orders(account);
+    order_ids.push(order_id);
+    write_open_orders(account, order_ids);
+}
 
-    let order_data = encode_order(caller, SIDE_SELL, price, amount, U512::zero(), STATUS_OPEN);
+/// Drop an order from its owner's open orders once it is filled or cancelled
+fn close_user_order(account: AccountHash, order_id: u64) {
+    let mut order_ids = read_open_orders(account);
+    order_ids.retain(|id| *id != order_id);
+    write_open_orders(account, order_ids);
+}
 
-    // Store order
-    let orders_uref = get_uref(DICT_ORDERS);
-    storage::dictionary_put(orders_uref, &order_id.to_string(), order_data);
+/// This contract's own key, as seen by the token contract
+/// CEP-18 identifies a calling contract by its package hash, not its contract hash.
+fn get_self_key() -> Key {
+    let self_uref = get_uref(KEY_SELF_PACKAGE);
+    let package_hash: ContractPackageHash =
+        storage::read::<Option<ContractPackageHash>>(self_uref)
+            .unwrap_or_revert()
+            .flatten()
+            .unwrap_or_revert_with(OrderBookError::MissingKey);
+    Key::Hash(package_hash.value())
+}
 
-    // Update best ask if this is lower
-    let current_best_ask = get_best_ask();
-    if price < current_best_ask {
-        set_best_ask(price);
+/// CEP-18 amounts are U256, internal balances are U512
+fn to_token_amount(amount: U512) -> U256 {
+    let mut bytes = [0u8; 64];
+    amount.to_little_endian(&mut bytes);
+    if bytes[32..].iter().any(|byte| *byte != 0) {
+        runtime::revert(OrderBookError::MathOverflow);
     }
-
-    // Return order ID
-    runtime::ret(CLValue::from_t(order_id).unwrap_or_revert());
+    U256::from_little_endian(&bytes[..32])
 }
 
-/// Cancel an open order
-#[no_mangle]
-pub extern "C" fn cancel_order() {
-    let caller = runtime::get_caller();
-    let order_id: u64 = runtime::get_named_arg("order_id");
+fn get_block_time() -> u64 {
+    runtime::get_blocktime().into()
+}
 
+fn read_order(order_id: u64) -> Order {
     let orders_uref = get_uref(DICT_ORDERS);
-    let order_data: String = storage::dictionary_get(orders_uref, &order_id.to_string())
+    storage::dictionary_get(orders_uref, &order_id.to_string())
         .unwrap_or_revert()
-        .unwrap_or_revert_with(OrderBookError::OrderNotFound);
+        .unwrap_or_revert_with(OrderBookError::OrderNotFound)
+}
 
-    // Parse order data
-    let parts: Vec<&str> = order_data.split(',').collect();
-    if parts.len() < 6 {
-        runtime::revert(OrderBookError::OrderNotFound);
-    }
+fn write_order(order_id: u64, order: &Order) {
+    let orders_uref = get_uref(DICT_ORDERS);
+    storage::dictionary_put(orders_uref, &order_id.to_string(), order.clone());
+}
 
-    // Verify caller is owner (parts[0] contains account hash)
-    let owner_str = parts[0];
-    if !owner_str.contains(&caller.to_string()[13..]) {
-        // Skip "account-hash-" prefix
-        runtime::revert(OrderBookError::NotAuthorized);
-    }
+/// Base units in one whole token of a market
+fn get_token_scale(market_id: u64) -> U512 {
+    let decimals: u8 = read_market_value(market_id, KEY_TOKEN_DECIMALS)
+        .unwrap_or_revert_with(OrderBookError::MissingKey);
+    U512::exp10(decimals as usize)
+}
 
-    let side: u8 = parts[1].parse().unwrap_or(255);
-    let _price: U512 = parts[2].parse().unwrap_or(U512::zero());
-    let amount: U512 = parts[3].parse().unwrap_or(U512::zero());
-    let filled: U512 = parts[4].parse().unwrap_or(U512::zero());
-    let status: u8 = parts[5].parse().unwrap_or(255);
+/// CSPR value of `amount` tokens at `price`, rounded down
+/// Used for everything the book pays out or refunds
+fn order_cost(market_id: u64, price: U512, amount: U512) -> U512 {
+    price
+        .checked_mul(amount)
+        .unwrap_or_revert_with(OrderBookError::MathOverflow)
+        / get_token_scale(market_id)
+}
 
-    if status != STATUS_OPEN && status != STATUS_PARTIAL {
-        runtime::revert(OrderBookError::OrderAlreadyFilled);
-    }
+/// CSPR value of `amount` tokens at `price`, rounded up
+/// Used for escrow the book takes in
+fn order_cost_up(market_id: u64, price: U512, amount: U512) -> U512 {
+    let scale = get_token_scale(market_id);
+    let value = price
+        .checked_mul(amount)
+        .unwrap_or_revert_with(OrderBookError::MathOverflow);
+    (value + scale - U512::one()) / scale
+}
 
-    let unfilled = amount - filled;
+/// `bps` basis points of `value`, rounded down
+fn fee_on(value: U512, bps: u32) -> U512 {
+    value * U512::from(bps) / U512::from(BPS_DENOMINATOR)
+}
 
-    // Return escrowed funds
-    if side == SIDE_BUY {
-        // Return CSPR
-        let total_refund = _price * unfilled / U512::from(1_000_000_000u64);
-        let escrow_purse = get_uref(KEY_CSPR_PURSE);
-        system::transfer_from_purse_to_account(escrow_purse, caller, total_refund, None)
-            .unwrap_or_revert_with(OrderBookError::TransferFailed);
-    } else {
-        // Return tokens
-        let current_balance = get_token_balance(caller);
-        set_token_balance(caller, current_balance + unfilled);
-    }
+/// CSPR a buy order escrows for `amount` tokens: its cost plus the largest possible fee
+fn buy_escrow(market_id: u64, price: U512, amount: U512) -> U512 {
+    let cost = order_cost_up(market_id, price, amount);
+    cost + fee_on(cost, MAX_FEE_BPS)
+}
 
-    // Mark order as cancelled
-    let cancelled_order =
-        encode_order(caller, side, _price, amount, filled, STATUS_CANCELLED);
-    storage::dictionary_put(orders_uref, &order_id.to_string(), cancelled_order);
+/// Part of a buy order's escrow that backs `amount` tokens, rounded down
+/// Never more than `buy_escrow` took in, whatever the split into fills.
+fn buy_refund(market_id: u64, price: U512, amount: U512) -> U512 {
+    let cost = order_cost(market_id, price, amount);
+    cost + fee_on(cost, MAX_FEE_BPS)
 }
 
-/// Get order details
-#[no_mangle]
-pub extern "C" fn get_order() {
-    let order_id: u64 = runtime::get_named_arg("order_id");
+/// Current (maker, taker) fees in basis points
+fn read_fee_schedule() -> (i32, u32) {
+    let maker_uref = get_uref(KEY_MAKER_FEE_BPS);
+    let taker_uref = get_uref(KEY_TAKER_FEE_BPS);
+    let maker_fee_bps: i32 = storage::read(maker_uref).unwrap_or_revert().unwrap_or(0);
+    let taker_fee_bps: u32 = storage::read(taker_uref).unwrap_or_revert().unwrap_or(0);
+    (maker_fee_bps, taker_fee_bps)
+}
 
-    let orders_uref = get_uref(DICT_ORDERS);
-    let order_data: String = storage::dictionary_get(orders_uref, &order_id.to_string())
-        .unwrap_or_revert()
-        .unwrap_or_revert_with(OrderBookError::OrderNotFound);
+fn get_fee_balance(market_id: u64) -> U512 {
+    read_market_value(market_id, KEY_FEE_BALANCE).unwrap_or(U512::zero())
+}
 
-    runtime::ret(CLValue::from_t(order_data).unwrap_or_revert());
+fn set_fee_balance(market_id: u64, balance: U512) {
+    write_market_value(market_id, KEY_FEE_BALANCE, balance);
 }
 
-/// Get best bid price
-#[no_mangle]
-pub extern "C" fn get_best_bid_ep() {
-    let best_bid = get_best_bid();
-    runtime::ret(CLValue::from_t(best_bid).unwrap_or_revert());
+// Grid of a market: any price and amount by default, no minimum notional
+fn get_tick_size(market_id: u64) -> U512 {
+    read_market_value(market_id, KEY_TICK_SIZE).unwrap_or(U512::one())
 }
 
-/// Get best ask price
-#[no_mangle]
-pub extern "C" fn get_best_ask_ep() {
-    let best_ask = get_best_ask();
-    runtime::ret(CLValue::from_t(best_ask).unwrap_or_revert());
+fn get_lot_size(market_id: u64) -> U512 {
+    read_market_value(market_id, KEY_LOT_SIZE).unwrap_or(U512::one())
 }
 
-/// Deposit tokens to the order book (for selling)
-#[no_mangle]
-pub extern "C" fn deposit_tokens() {
-    let caller = runtime::get_caller();
-    let amount: U512 = runtime::get_named_arg("amount");
+fn get_min_notional(market_id: u64) -> U512 {
+    read_market_value(market_id, KEY_MIN_NOTIONAL).unwrap_or(U512::zero())
+}
 
-    if amount == U512::zero() {
-        runtime::revert(OrderBookError::InvalidAmount);
+fn check_lot(market_id: u64, amount: U512) {
+    if amount % get_lot_size(market_id) != U512::zero() {
+        runtime::revert(OrderBookError::AmountOffLot);
     }
+}
 
-    // In a real implementation, this would transfer CEP-18 tokens
-    // For now, we just track the balance internally
-    let current_balance = get_token_balance(caller);
-    set_token_balance(caller, current_balance + amount);
+/// Revert unless a limit order sits on the market's grid and meets its minimum notional
+fn check_order_grid(market_id: u64, price: U512, amount: U512) {
+    if price % get_tick_size(market_id) != U512::zero() {
+        runtime::revert(OrderBookError::PriceOffTick);
+    }
+    check_lot(market_id, amount);
+    if order_cost(market_id, price, amount) < get_min_notional(market_id) {
+        runtime::revert(OrderBookError::BelowMinNotional);
+    }
 }
 
-/// Withdraw tokens from the order book
-#[no_mangle]
-pub extern "C" fn withdraw_tokens() {
-    let caller = runtime::get_caller();
-    let amount: U512 = runtime::get_named_arg("amount");
+fn get_escrow_purse(market_id: u64) -> URef {
+    get_uref(&market_key(market_id, KEY_CSPR_PURSE))
+}
 
+fn pay_cspr(market_id: u64, recipient: AccountHash, amount: U512) {
     if amount == U512::zero() {
-        runtime::revert(OrderBookError::InvalidAmount);
+        return;
     }
+    let escrow_purse = get_escrow_purse(market_id);
+    system::transfer_from_purse_to_account(escrow_purse, recipient, amount, None)
+        .unwrap_or_revert_with(OrderBookError::TransferFailed);
+}
 
-    let current_balance = get_token_balance(caller);
-    if current_balance < amount {
-        runtime::revert(OrderBookError::InsufficientFunds);
+/// Most a routed buy of `amount` tokens may spend on the curve so that, together with
+/// the CSPR `spent` on its book fills (fees included), it pays no more than
+/// `limit_price` per token
+fn max_route_cost(market_id: u64, limit_price: U512, amount: U512, spent: U512) -> U512 {
+    order_cost(market_id, limit_price, amount).saturating_sub(spent)
+}
+
+/// Buy `amount` tokens on the market's bonding curve if they cost at most `max_cost`
+/// The curve leg is all or nothing: when the curve quotes more, nothing is bought and
+/// the caller refunds the remainder, so the book fills still stand. The curve only
+/// ever sees a temporary purse holding its quote, whatever it leaves goes back to
+/// escrow. It credits its own balance of the deploying account.
+/// Returns the CSPR spent, `None` if the curve was skipped.
+fn buy_on_curve(market_id: u64, amount: U512, max_cost: U512) -> Option<U512> {
+    let amm = read_market_value::<Option<ContractHash>>(market_id, KEY_AMM)
+        .flatten()
+        .unwrap_or_revert_with(OrderBookError::AmmNotSet);
+
+    let cost = runtime::call_contract::<U512>(
+        amm,
+        AMM_GET_BUY_COST,
+        runtime_args! {
+            "token_amount" => amount,
+        },
+    );
+    if cost > max_cost {
+        // Not enough left under the limit or budget
+        return None;
     }
 
-    // In a real implementation, this would transfer CEP-18 tokens back
-    set_token_balance(caller, current_balance - amount);
+    let escrow_purse = get_escrow_purse(market_id);
+    let route_purse = system::create_purse();
+    system::transfer_from_purse_to_purse(escrow_purse, route_purse, cost, None)
+        .unwrap_or_revert_with(OrderBookError::TransferFailed);
+
+    runtime::call_contract::<()>(
+        amm,
+        AMM_BUY,
+        runtime_args! {
+            "token_amount" => amount,
+            "max_cost" => cost,
+            "payment_purse" => route_purse,
+        },
+    );
+
+    let unused = system::get_purse_balance(route_purse)
+        .unwrap_or_revert_with(OrderBookError::MissingKey);
+    if unused > U512::zero() {
+        system::transfer_from_purse_to_purse(route_purse, escrow_purse, unused, None)
+            .unwrap_or_revert_with(OrderBookError::TransferFailed);
+    }
+    Some(cost - unused)
 }
 
 // ============================================================================
-// Contract Installation
+// Matching
 // ============================================================================
 
-fn build_entry_points() -> EntryPoints {
-    let mut entry_points = EntryPoints::new();
+// Active prices per side, best first
+// Bids: highest price first, asks: lowest price first
+fn prices_key(side: OrderSide) -> &'static str {
+    if side == OrderSide::Buy {
+        KEY_BID_PRICES
+    } else {
+        KEY_ASK_PRICES
+    }
+}
 
-    entry_points.add_entry_point(EntryPoint::new(
+fn read_prices(market_id: u64, side: OrderSide) -> Vec<U512> {
+    read_market_value(market_id, prices_key(side)).unwrap_or_default()
+}
+
+fn write_prices(market_id: u64, side: OrderSide, prices: Vec<U512>) {
+    write_market_value(market_id, prices_key(side), prices);
+}
+
+/// Whether `a` is a strictly better price than `b` for orders on `side`
+fn is_better_price(side: OrderSide, a: U512, b: U512) -> bool {
+    if side == OrderSide::Buy {
+        a > b
+    } else {
+        a < b
+    }
+}
+
+// Each price level is a FIFO queue of order IDs, oldest first
+fn level_key(market_id: u64, side: OrderSide, price: U512) -> String {
+    format!("{}_{}_{}", market_id, side as u8, price)
+}
+
+fn read_level(market_id: u64, side: OrderSide, price: U512) -> Vec<u64> {
+    let levels_uref = get_uref(DICT_PRICE_LEVELS);
+    storage::dictionary_get(levels_uref, &level_key(market_id, side, price))
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+fn write_level(market_id: u64, side: OrderSide, price: U512, level: Vec<u64>) {
+    let levels_uref = get_uref(DICT_PRICE_LEVELS);
+    storage::dictionary_put(levels_uref, &level_key(market_id, side, price), level);
+}
+
+/// Recompute best bid or ask from the live price levels
+fn update_best_price(market_id: u64, side: OrderSide) {
+    let best = read_prices(market_id, side).first().copied();
+    if side == OrderSide::Buy {
+        set_best_bid(market_id, best.unwrap_or(U512::zero()));
+    } else {
+        set_best_ask(market_id, best.unwrap_or(U512::MAX));
+    }
+}
+
+/// Append a resting order to the back of its price level
+fn add_to_level(market_id: u64, side: OrderSide, price: U512, order_id: u64) {
+    let mut level = read_level(market_id, side, price);
+    if level.is_empty() {
+        let mut prices = read_prices(market_id, side);
+        let position = prices
+            .iter()
+            .position(|level_price| is_better_price(side, price, *level_price))
+            .unwrap_or(prices.len());
+        prices.insert(position, price);
+        write_prices(market_id, side, prices);
+        update_best_price(market_id, side);
+    }
+    level.push(order_id);
+    write_level(market_id, side, price, level);
+}
+
+/// Remove a resting order from its price level, dropping the level once empty
+fn remove_from_level(market_id: u64, side: OrderSide, price: U512, order_id: u64) {
+    let mut level = read_level(market_id, side, price);
+    level.retain(|id| *id != order_id);
+    let emptied = level.is_empty();
+    write_level(market_id, side, price, level);
+
+    if emptied {
+        let mut prices = read_prices(market_id, side);
+        prices.retain(|level_price| *level_price != price);
+        write_prices(market_id, side, prices);
+        update_best_price(market_id, side);
+    }
+}
+
+fn get_trade_counter() -> u64 {
+    let counter_uref = get_uref(KEY_TRADE_COUNTER);
+    storage::read(counter_uref)
+        .unwrap_or_revert()
+        .unwrap_or(0u64)
+}
+
+/// Append a fill to the trade log
+fn record_trade(
+    market: ContractHash,
+    taker_id: u64,
+    maker_id: u64,
+    maker_side: OrderSide,
+    price: U512,
+    amount: U512,
+) {
+    let trade_id = get_trade_counter() + 1;
+    storage::write(get_uref(KEY_TRADE_COUNTER), trade_id);
+
+    let (buy_order_id, sell_order_id) = if maker_side == OrderSide::Sell {
+        (taker_id, maker_id)
+    } else {
+        (maker_id, taker_id)
+    };
+    let trade = Trade {
+        market,
+        buy_order_id,
+        sell_order_id,
+        price,
+        amount,
+        maker_side,
+        timestamp: get_block_time(),
+    };
+
+    let trades_uref = get_uref(DICT_TRADES);
+    storage::dictionary_put(trades_uref, &trade_id.to_string(), trade);
+}
+
+fn get_self_trade_mode(account: AccountHash) -> SelfTradeMode {
+    let modes_uref = get_uref(DICT_SELF_TRADE_MODES);
+    storage::dictionary_get(modes_uref, &account.to_string())
+        .unwrap_or_revert()
+        .unwrap_or(SelfTradeMode::CancelNewest)
+}
+
+fn crosses(taker: &Order, maker_price: U512) -> bool {
+    if taker.side == OrderSide::Buy {
+        taker.price >= maker_price
+    } else {
+        taker.price <= maker_price
+    }
+}
+
+/// Move funds for a fill of `amount` tokens at the maker's price and collect fees
+/// The seller's proceeds, and the part of a resting buyer's fee allowance this fill
+/// did not use, are credited to their CSPR balances.
+/// Returns (trade value, CSPR drawn from a buying taker's escrow)
+fn settle_fill(
+    market_id: u64,
+    taker: &Order,
+    maker: &Order,
+    amount: U512,
+    price: U512,
+) -> (U512, U512) {
+    let (buyer, seller) = if taker.side == OrderSide::Buy {
+        (taker.owner, maker.owner)
+    } else {
+        (maker.owner, taker.owner)
+    };
+
+    let cost = order_cost(market_id, price, amount);
+    let (maker_fee_bps, taker_fee_bps) = read_fee_schedule();
+
+    let mut buyer_pays = cost;
+    let mut seller_receives = cost;
+    let mut fees_collected = fee_on(cost, taker_fee_bps);
+
+    // Taker fee
+    if taker.side == OrderSide::Buy {
+        buyer_pays += fees_collected;
+    } else {
+        seller_receives -= fees_collected;
+    }
+
+    // Maker fee, or rebate out of the taker fee when negative
+    let maker_fee = fee_on(cost, maker_fee_bps.unsigned_abs());
+    match (maker_fee_bps >= 0, maker.side) {
+        (true, OrderSide::Buy) => buyer_pays += maker_fee,
+        (true, OrderSide::Sell) => seller_receives -= maker_fee,
+        (false, OrderSide::Buy) => buyer_pays -= maker_fee,
+        (false, OrderSide::Sell) => seller_receives += maker_fee,
+    }
+    if maker_fee_bps >= 0 {
+        fees_collected += maker_fee;
+    } else {
+        fees_collected -= maker_fee;
+    }
+    set_fee_balance(market_id, get_fee_balance(market_id) + fees_collected);
+
+    // Seller is credited from the buyer's escrowed CSPR
+    credit_cspr(market_id, seller, seller_receives);
+
+    // Buyer receives the seller's escrowed tokens
+    let buyer_balance = get_token_balance(market_id, buyer);
+    set_token_balance(market_id, buyer, buyer_balance + amount);
+
+    if maker.side == OrderSide::Buy {
+        // A resting buy fills at its own price, only the fee allowance is left over
+        credit_cspr(market_id, buyer, buy_refund(market_id, price, amount) - buyer_pays);
+        (cost, U512::zero())
+    } else {
+        (cost, buyer_pays)
+    }
+}
+
+/// Match a new order against the opposite side of the book
+/// Fills at the resting order's price, best price first then oldest first.
+/// A buy taker with a `budget` stops once it cannot afford any more tokens and fees.
+/// Meeting a resting order of the same owner applies the owner's self-trade mode;
+/// a cancelled taker comes back with `Cancelled` status and its remainder unrefunded.
+/// Every fill is recorded in the trade log.
+/// Returns (total trade value, CSPR drawn from a buying taker's escrow)
+fn match_order(
+    market_id: u64,
+    taker_id: u64,
+    taker: &mut Order,
+    budget: Option<U512>,
+) -> (U512, U512) {
+    let maker_side = taker.side.opposite();
+    let mut prices = read_prices(market_id, maker_side);
+    let mut levels_cleared = 0usize;
+    let mut traded = U512::zero();
+    let mut spent = U512::zero();
+    // Set once the taker runs out of budget or is cancelled by self-trade prevention
+    let mut stopped = false;
+    let mut last_fill_price = None;
+    let now = get_block_time();
+    let (_, taker_fee_bps) = read_fee_schedule();
+    let lot_size = get_lot_size(market_id);
+    let token_scale = get_token_scale(market_id);
+
+    while !stopped && taker.remaining() > U512::zero() && levels_cleared < prices.len() {
+        let level_price = prices[levels_cleared];
+        if !crosses(taker, level_price) {
+            break;
+        }
+
+        let mut level = read_level(market_id, maker_side, level_price);
+        let mut consumed = 0usize;
+
+        while taker.remaining() > U512::zero() && consumed < level.len() {
+            let maker_id = level[consumed];
+            let mut maker = read_order(maker_id);
+            if !maker.is_open() {
+                consumed += 1;
+                continue;
+            }
+            if maker.is_expired(now) {
+                expire_order(market_id, maker_id, maker);
+                consumed += 1;
+                continue;
+            }
+
+            if maker.owner == taker.owner {
+                let mode = get_self_trade_mode(taker.owner);
+                if mode != SelfTradeMode::CancelNewest {
+                    // Cancel the resting order and refund it
+                    refund_unfilled(market_id, &maker, maker.remaining());
+                    close_user_order(maker.owner, maker_id);
+                    maker.status = OrderStatus::Cancelled;
+                    write_order(maker_id, &maker);
+                    consumed += 1;
+                }
+                if mode != SelfTradeMode::CancelOldest {
+                    taker.status = OrderStatus::Cancelled;
+                    stopped = true;
+                    break;
+                }
+                continue;
+            }
+
+            let mut fill = taker.remaining().min(maker.remaining());
+            if let Some(budget) = budget {
+                let affordable = (budget - spent)
+                    * token_scale
+                    * U512::from(BPS_DENOMINATOR)
+                    / (level_price * U512::from(BPS_DENOMINATOR + u64::from(taker_fee_bps)));
+                // Whole lots only, so the maker is never left with dust
+                fill = fill.min(affordable - affordable % lot_size);
+                if fill == U512::zero() {
+                    stopped = true;
+                    break;
+                }
+            }
+
+            let (value, paid) = settle_fill(market_id, taker, &maker, fill, level_price);
+            traded += value;
+            spent += paid;
+            record_trade(taker.market, taker_id, maker_id, maker_side, level_price, fill);
+            last_fill_price = Some(level_price);
+
+            taker.filled += fill;
+            maker.filled += fill;
+            maker.refresh_status();
+            write_order(maker_id, &maker);
+
+            if !maker.is_open() {
+                close_user_order(maker.owner, maker_id);
+                consumed += 1;
+            }
+        }
+
+        level.drain(..consumed);
+        if level.is_empty() {
+            levels_cleared += 1;
+        }
+        write_level(market_id, maker_side, level_price, level);
+    }
+
+    if levels_cleared > 0 {
+        prices.drain(..levels_cleared);
+        write_prices(market_id, maker_side, prices);
+        update_best_price(market_id, maker_side);
+    }
+    if let Some(price) = last_fill_price {
+        set_last_price(market_id, price);
+    }
+    if taker.status != OrderStatus::Cancelled {
+        taker.refresh_status();
+    }
+    (traded, spent)
+}
+
+/// Return the escrow backing `amount` unfilled tokens of an order to its owner
+fn refund_unfilled(market_id: u64, order: &Order, amount: U512) {
+    if order.side == OrderSide::Buy {
+        // Return CSPR, fee allowance included
+        refund_cspr(market_id, order.owner, buy_refund(market_id, order.price, amount));
+    } else {
+        // Return tokens
+        let current_balance = get_token_balance(market_id, order.owner);
+        set_token_balance(market_id, order.owner, current_balance + amount);
+    }
+}
+
+/// Refund an expired order and close it
+/// The caller is responsible for removing it from its price level
+fn expire_order(market_id: u64, order_id: u64, mut order: Order) {
+    let unfilled = order.remaining();
+    if unfilled > U512::zero() {
+        refund_unfilled(market_id, &order, unfilled);
+    }
+    close_user_order(order.owner, order_id);
+    order.status = OrderStatus::Expired;
+    write_order(order_id, &order);
+}
+
+fn read_expiring_orders(market_id: u64) -> Vec<u64> {
+    read_market_value(market_id, KEY_EXPIRING_ORDERS).unwrap_or_default()
+}
+
+fn write_expiring_orders(market_id: u64, expiring: Vec<u64>) {
+    write_market_value(market_id, KEY_EXPIRING_ORDERS, expiring);
+}
+
+/// Whether an order at `price` would match the opposite side right away
+fn would_take_liquidity(market_id: u64, side: OrderSide, price: U512) -> bool {
+    match read_prices(market_id, side.opposite()).first() {
+        Some(best) => {
+            if side == OrderSide::Buy {
+                price >= *best
+            } else {
+                price <= *best
+            }
+        }
+        None => false,
+    }
+}
+
+/// Reject a limit order with a zero price or amount, or one off the market's grid
+fn check_limit_order(market_id: u64, price: U512, amount: U512) {
+    if price == U512::zero() {
+        runtime::revert(OrderBookError::InvalidPrice);
+    }
+    if amount == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+    check_order_grid(market_id, price, amount);
+}
+
+/// Escrow a buy limit order's cost and fee allowance from `payment_purse`
+fn escrow_buy(market_id: u64, payment_purse: URef, price: U512, amount: U512) {
+    let total_cost = buy_escrow(market_id, price, amount);
+    let escrow_purse = get_escrow_purse(market_id);
+    system::transfer_from_purse_to_purse(payment_purse, escrow_purse, total_cost, None)
+        .unwrap_or_revert_with(OrderBookError::TransferFailed);
+}
+
+/// Lock deposited tokens for a sell limit order
+fn lock_tokens(market_id: u64, owner: AccountHash, amount: U512) {
+    let user_balance = get_token_balance(market_id, owner);
+    if user_balance < amount {
+        runtime::revert(OrderBookError::InsufficientFunds);
+    }
+    set_token_balance(market_id, owner, user_balance - amount);
+}
+
+/// Cancel one of `owner`'s open orders and refund what is left of its escrow
+fn cancel_open_order(owner: AccountHash, order_id: u64) {
+    let mut order = read_order(order_id);
+
+    if order.owner != owner {
+        runtime::revert(OrderBookError::NotAuthorized);
+    }
+
+    if !order.is_open() {
+        runtime::revert(OrderBookError::OrderAlreadyFilled);
+    }
+
+    let unfilled = order.remaining();
+    let market_id = get_market_id(order.market);
+
+    // Take the order out of its price level and the owner's open orders
+    remove_from_level(market_id, order.side, order.price, order_id);
+    close_user_order(owner, order_id);
+
+    // Return escrowed funds
+    refund_unfilled(market_id, &order, unfilled);
+
+    // Mark order as cancelled
+    order.status = OrderStatus::Cancelled;
+    write_order(order_id, &order);
+}
+
+/// Create an order whose escrow is already held, match it and apply its order type
+/// With `route_to_amm`, an IOC buy takes what the book could not fill from the curve,
+/// as long as the whole route stays within its limit price.
+/// Returns the new order ID
+#[allow(clippy::too_many_arguments)]
+fn place_order(
+    market: ContractHash,
+    owner: AccountHash,
+    side: OrderSide,
+    price: U512,
+    amount: U512,
+    order_type: OrderType,
+    expires_at: u64,
+    route_to_amm: bool,
+) -> u64 {
+    let market_id = get_market_id(market);
+    if order_type == OrderType::PostOnly && would_take_liquidity(market_id, side, price) {
+        runtime::revert(OrderBookError::WouldTakeLiquidity);
+    }
+    // Only IOC buys can be routed, the book cannot hand escrowed tokens to the curve
+    if route_to_amm && (order_type != OrderType::ImmediateOrCancel || side == OrderSide::Sell) {
+        runtime::revert(OrderBookError::InvalidRoute);
+    }
+
+    let now = get_block_time();
+    if expires_at != 0 && expires_at <= now {
+        runtime::revert(OrderBookError::InvalidExpiry);
+    }
+
+    // Create order
+    let order_id = get_order_counter() + 1;
+    set_order_counter(order_id);
+
+    let mut order = Order {
+        market,
+        owner,
+        side,
+        price,
+        amount,
+        filled: U512::zero(),
+        routed: U512::zero(),
+        status: OrderStatus::Open,
+        created_at: now,
+        expires_at,
+    };
+
+    // Match against the opposite side
+    let (_, spent) = match_order(market_id, order_id, &mut order, None);
+
+    // A buy escrowed at its own limit plus the fee cap, this much of it is unused
+    let mut escrow_left = if side == OrderSide::Buy {
+        buy_refund(market_id, price, order.filled) - spent
+    } else {
+        U512::zero()
+    };
+
+    // Buy the remainder on the curve, unless self-trade prevention cancelled it.
+    // If the curve is too dear the remainder is refunded below like any IOC's.
+    let unfilled = order.remaining();
+    if route_to_amm && unfilled > U512::zero() && order.status != OrderStatus::Cancelled {
+        let route_escrow = buy_refund(market_id, price, unfilled);
+        let max_cost =
+            max_route_cost(market_id, price, amount, spent).min(escrow_left + route_escrow);
+        if let Some(route_cost) = buy_on_curve(market_id, unfilled, max_cost) {
+            escrow_left = escrow_left + route_escrow - route_cost;
+            order.routed = unfilled;
+            order.status = OrderStatus::Filled;
+        }
+    }
+
+    if side == OrderSide::Buy {
+        refund_cspr(market_id, owner, escrow_left);
+    }
+
+    let unfilled = order.remaining();
+    if unfilled > U512::zero() {
+        if order_type == OrderType::FillOrKill {
+            // Reverting undoes every fill made above
+            runtime::revert(OrderBookError::OrderNotFilled);
+        }
+        // IOC never rests, and self-trade prevention may have cancelled the order
+        if order_type == OrderType::ImmediateOrCancel || order.status == OrderStatus::Cancelled {
+            refund_unfilled(market_id, &order, unfilled);
+            order.status = OrderStatus::Cancelled;
+        }
+    }
+
+    // Store order
+    write_order(order_id, &order);
+
+    // Index the order and rest the remainder in the book
+    append_order_history(owner, order_id);
+    if order.is_open() {
+        index_open_order(owner, order_id);
+        add_to_level(market_id, side, price, order_id);
+
+        if expires_at != 0 {
+            let mut expiring = read_expiring_orders(market_id);
+            expiring.push(order_id);
+            write_expiring_orders(market_id, expiring);
+        }
+    }
+
+    order_id
+}
+
+/// Sweep the opposite side of the book up to `worst_price`
+/// Buys are also capped by `budget`, whose unused part is refunded. With `route`, a
+/// buy takes what the book could not fill from the bonding curve if it fits within
+/// both limits. Whatever does not fill is refunded and the order is closed.
+/// Returns (amount filled on the book, amount bought on the curve, average book price)
+fn execute_market_order(
+    market: ContractHash,
+    owner: AccountHash,
+    side: OrderSide,
+    amount: U512,
+    worst_price: U512,
+    budget: Option<U512>,
+    route: bool,
+) -> (U512, U512, U512) {
+    let market_id = get_market_id(market);
+    let order_id = get_order_counter() + 1;
+    set_order_counter(order_id);
+
+    let mut order = Order {
+        market,
+        owner,
+        side,
+        price: worst_price,
+        amount,
+        filled: U512::zero(),
+        routed: U512::zero(),
+        status: OrderStatus::Open,
+        created_at: get_block_time(),
+        expires_at: 0,
+    };
+
+    let (traded, spent) = match_order(market_id, order_id, &mut order, budget);
+
+    // Buy the rest on the curve if asked, then refund unused escrow
+    match budget {
+        Some(budget) => {
+            let mut budget_left = budget - spent;
+            let unfilled = order.remaining();
+            if route && unfilled > U512::zero() && order.status != OrderStatus::Cancelled {
+                let max_cost = max_route_cost(market_id, worst_price, amount, spent);
+                if let Some(route_cost) =
+                    buy_on_curve(market_id, unfilled, max_cost.min(budget_left))
+                {
+                    budget_left -= route_cost;
+                    order.routed = unfilled;
+                    order.status = OrderStatus::Filled;
+                }
+            }
+            pay_cspr(market_id, owner, budget_left);
+        }
+        None => {
+            let unfilled = order.remaining();
+            if unfilled > U512::zero() {
+                refund_unfilled(market_id, &order, unfilled);
+            }
+        }
+    }
+
+    if order.is_open() {
+        order.status = OrderStatus::Cancelled;
+    }
+    let filled = order.filled;
+    write_order(order_id, &order);
+    append_order_history(owner, order_id);
+
+    let average_price = if filled == U512::zero() {
+        U512::zero()
+    } else {
+        traded * get_token_scale(market_id) / filled
+    };
+    (filled, order.routed, average_price)
+}
+
+// ============================================================================
+// Conditional Orders
+// ============================================================================
+
+fn read_conditional(conditional_id: u64) -> ConditionalOrder {
+    let conditionals_uref = get_uref(DICT_CONDITIONAL_ORDERS);
+    storage::dictionary_get(conditionals_uref, &conditional_id.to_string())
+        .unwrap_or_revert()
+        .unwrap_or_revert_with(OrderBookError::OrderNotFound)
+}
+
+fn write_conditional(conditional_id: u64, conditional: ConditionalOrder) {
+    let conditionals_uref = get_uref(DICT_CONDITIONAL_ORDERS);
+    storage::dictionary_put(conditionals_uref, &conditional_id.to_string(), conditional);
+}
+
+fn read_pending_conditionals(market_id: u64) -> Vec<u64> {
+    read_market_value(market_id, KEY_PENDING_CONDITIONALS).unwrap_or_default()
+}
+
+fn write_pending_conditionals(market_id: u64, pending: Vec<u64>) {
+    write_market_value(market_id, KEY_PENDING_CONDITIONALS, pending);
+}
+
+/// Store a conditional order whose escrow and bounty are already held
+/// Returns the conditional order ID
+fn store_conditional(market_id: u64, conditional: ConditionalOrder) -> u64 {
+    let counter_uref = get_uref(KEY_CONDITIONAL_COUNTER);
+    let conditional_id = storage::read::<u64>(counter_uref)
+        .unwrap_or_revert()
+        .unwrap_or(0u64)
+        + 1;
+    storage::write(counter_uref, conditional_id);
+
+    write_conditional(conditional_id, conditional);
+
+    let mut pending = read_pending_conditionals(market_id);
+    pending.push(conditional_id);
+    write_pending_conditionals(market_id, pending);
+
+    conditional_id
+}
+
+/// Build a pending conditional order from the entry point's named args
+/// Its bounty is the current keeper bounty
+fn read_conditional_args(owner: AccountHash, side: OrderSide) -> ConditionalOrder {
+    let market: ContractHash = runtime::get_named_arg("market");
+    let trigger_price: U512 = runtime::get_named_arg("trigger_price");
+    let trigger_above: bool = runtime::get_named_arg("trigger_above");
+    let price: U512 = runtime::get_named_arg("price"); // Limit price once triggered
+    let amount: U512 = runtime::get_named_arg("amount"); // Token amount
+
+    if trigger_price == U512::zero() || price == U512::zero() {
+        runtime::revert(OrderBookError::InvalidPrice);
+    }
+    if amount == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+    check_order_grid(get_market_id(market), price, amount);
+
+    ConditionalOrder {
+        market,
+        owner,
+        side,
+        trigger_price,
+        trigger_above,
+        price,
+        amount,
+        bounty: get_keeper_bounty(),
+        status: ConditionalStatus::Pending,
+        order_id: 0,
+        created_at: get_block_time(),
+    }
+}
+
+// ============================================================================
+// Entry Points Implementation
+// ============================================================================
+
+/// List a market for the CEP-18 token `token` (admin or launchpad only)
+/// Creates its CSPR escrow purse and empty book. `decimals` defaults to the
+/// token contract's own `decimals`.
+#[no_mangle]
+pub extern "C" fn create_market() {
+    only_launchpad_or_admin();
+
+    let token: ContractHash = runtime::get_named_arg("token");
+    let decimals: Option<u8> = runtime::get_named_arg("decimals");
+
+    let decimals = decimals.unwrap_or_else(|| {
+        runtime::call_contract::<u8>(token, CEP18_DECIMALS, RuntimeArgs::new())
+    });
+    if decimals > MAX_TOKEN_DECIMALS {
+        runtime::revert(OrderBookError::InvalidDecimals);
+    }
+
+    let markets_uref = get_uref(DICT_MARKETS);
+    let existing: Option<u64> =
+        storage::dictionary_get(markets_uref, &token.to_string()).unwrap_or_revert();
+    if existing.is_some() {
+        runtime::revert(OrderBookError::MarketExists);
+    }
+
+    let counter_uref = get_uref(KEY_MARKET_COUNTER);
+    let market_id = storage::read::<u64>(counter_uref)
+        .unwrap_or_revert()
+        .unwrap_or(0u64)
+        + 1;
+    storage::write(counter_uref, market_id);
+    storage::dictionary_put(markets_uref, &token.to_string(), market_id);
+
+    // Each market keeps its CSPR in its own purse
+    let escrow_purse = system::create_purse();
+    runtime::put_key(&market_key(market_id, KEY_CSPR_PURSE), escrow_purse.into());
+
+    write_market_value(market_id, KEY_TOKEN_DECIMALS, decimals);
+    set_best_bid(market_id, U512::zero());
+    set_best_ask(market_id, U512::MAX);
+}
+
+/// Set a market's tick size, lot size and minimum notional (admin only)
+/// Applies to orders placed after the change, resting orders are left as they are.
+#[no_mangle]
+pub extern "C" fn set_market_params() {
+    only_admin();
+
+    let market: ContractHash = runtime::get_named_arg("market");
+    let tick_size: U512 = runtime::get_named_arg("tick_size");
+    let lot_size: U512 = runtime::get_named_arg("lot_size");
+    let min_notional: U512 = runtime::get_named_arg("min_notional");
+
+    if tick_size == U512::zero() {
+        runtime::revert(OrderBookError::InvalidPrice);
+    }
+    if lot_size == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+
+    let market_id = get_market_id(market);
+    write_market_value(market_id, KEY_TICK_SIZE, tick_size);
+    write_market_value(market_id, KEY_LOT_SIZE, lot_size);
+    write_market_value(market_id, KEY_MIN_NOTIONAL, min_notional);
+}
+
+/// Link a market to its bonding curve AMM for routing, `None` to unlink
+/// Only callable by admin or launchpad
+#[no_mangle]
+pub extern "C" fn set_market_amm() {
+    only_launchpad_or_admin();
+
+    let market: ContractHash = runtime::get_named_arg("market");
+    let amm: Option<ContractHash> = runtime::get_named_arg("amm");
+
+    let market_id = get_market_id(market);
+    write_market_value(market_id, KEY_AMM, amm);
+}
+
+/// Set the launchpad account allowed to create markets
+/// Only callable by admin
+#[no_mangle]
+pub extern "C" fn set_launchpad() {
+    only_admin();
+
+    let launchpad: AccountHash = runtime::get_named_arg("launchpad");
+    let launchpad_uref = get_uref(KEY_LAUNCHPAD);
+    storage::write(launchpad_uref, launchpad);
+}
+
+/// Place a buy limit order
+/// Escrows CSPR from the payment_purse: the order cost plus a `MAX_FEE_BPS` fee allowance
+/// `route_to_amm` (IOC only) buys the remainder on the market's bonding curve, or
+/// refunds it if the curve would push the average price above the limit
+#[no_mangle]
+pub extern "C" fn place_buy_order() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let price: U512 = runtime::get_named_arg("price"); // Price per token in motes
+    let amount: U512 = runtime::get_named_arg("amount"); // Token amount
+    let payment_purse: URef = runtime::get_named_arg("payment_purse");
+    let order_type: OrderType = runtime::get_named_arg("order_type");
+    let expires_at: u64 = runtime::get_named_arg("expires_at"); // 0 = never
+    let route_to_amm: bool = runtime::get_named_arg("route_to_amm");
+
+    let market_id = get_market_id(market);
+    check_limit_order(market_id, price, amount);
+
+    // Transfer the cost and fee allowance to the market's escrow
+    escrow_buy(market_id, payment_purse, price, amount);
+
+    // Create order and match against resting asks
+    let order_id = place_order(
+        market,
+        caller,
+        OrderSide::Buy,
+        price,
+        amount,
+        order_type,
+        expires_at,
+        route_to_amm,
+    );
+
+    // Return order ID
+    runtime::ret(CLValue::from_t(order_id).unwrap_or_revert());
+}
+
+/// Place a sell limit order
+/// Requires tokens to be deposited first
+#[no_mangle]
+pub extern "C" fn place_sell_order() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let price: U512 = runtime::get_named_arg("price"); // Price per token in motes
+    let amount: U512 = runtime::get_named_arg("amount"); // Token amount
+    let order_type: OrderType = runtime::get_named_arg("order_type");
+    let expires_at: u64 = runtime::get_named_arg("expires_at"); // 0 = never
+
+    let market_id = get_market_id(market);
+    check_limit_order(market_id, price, amount);
+
+    // Lock tokens (reduce available balance)
+    lock_tokens(market_id, caller, amount);
+
+    // Create order and match against resting bids
+    let order_id = place_order(
+        market,
+        caller,
+        OrderSide::Sell,
+        price,
+        amount,
+        order_type,
+        expires_at,
+        false,
+    );
+
+    // Return order ID
+    runtime::ret(CLValue::from_t(order_id).unwrap_or_revert());
+}
+
+/// Buy up to `amount` tokens at market
+/// Escrows `max_spend` from the payment_purse and refunds whatever is not spent.
+/// `max_spend` covers taker fees as well as the tokens.
+/// Never pays more than `worst_price` per token, on average across the whole route.
+/// With `route_to_amm`, the rest is bought on the market's bonding curve if that
+/// fits within both limits, otherwise it is refunded and the book fills stand.
+/// Returns (amount filled on the book, amount bought on the curve, average book price)
+#[no_mangle]
+pub extern "C" fn place_market_buy() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let amount: U512 = runtime::get_named_arg("amount"); // Token amount
+    let worst_price: U512 = runtime::get_named_arg("worst_price"); // Max price per token
+    let max_spend: U512 = runtime::get_named_arg("max_spend"); // Max CSPR in motes
+    let payment_purse: URef = runtime::get_named_arg("payment_purse");
+    let route_to_amm: bool = runtime::get_named_arg("route_to_amm");
+
+    if worst_price == U512::zero() {
+        runtime::revert(OrderBookError::InvalidPrice);
+    }
+    if amount == U512::zero() || max_spend == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+
+    let market_id = get_market_id(market);
+    check_lot(market_id, amount);
+
+    // Transfer the spend cap to the market's escrow
+    let escrow_purse = get_escrow_purse(market_id);
+    system::transfer_from_purse_to_purse(payment_purse, escrow_purse, max_spend, None)
+        .unwrap_or_revert_with(OrderBookError::TransferFailed);
+
+    let result = execute_market_order(
+        market,
+        caller,
+        OrderSide::Buy,
+        amount,
+        worst_price,
+        Some(max_spend),
+        route_to_amm,
+    );
+
+    runtime::ret(CLValue::from_t(result).unwrap_or_revert());
+}
+
+/// Sell up to `amount` deposited tokens at market
+/// Never sells below `worst_price` per token (0 = no limit)
+#[no_mangle]
+pub extern "C" fn place_market_sell() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let amount: U512 = runtime::get_named_arg("amount"); // Token amount
+    let worst_price: U512 = runtime::get_named_arg("worst_price"); // Min price per token
+
+    if amount == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+
+    let market_id = get_market_id(market);
+    check_lot(market_id, amount);
+
+    // Lock tokens (reduce available balance)
+    lock_tokens(market_id, caller, amount);
+
+    let (filled, _, average_price) =
+        execute_market_order(market, caller, OrderSide::Sell, amount, worst_price, None, false);
+
+    runtime::ret(CLValue::from_t((filled, average_price)).unwrap_or_revert());
+}
+
+/// Place a conditional buy order
+/// Escrows the order cost, fee allowance and keeper bounty from the payment_purse
+#[no_mangle]
+pub extern "C" fn place_conditional_buy() {
+    let caller = runtime::get_caller();
+    let conditional = read_conditional_args(caller, OrderSide::Buy);
+    let payment_purse: URef = runtime::get_named_arg("payment_purse");
+
+    let market_id = get_market_id(conditional.market);
+    let total_cost =
+        buy_escrow(market_id, conditional.price, conditional.amount) + conditional.bounty;
+
+    let escrow_purse = get_escrow_purse(market_id);
+    system::transfer_from_purse_to_purse(payment_purse, escrow_purse, total_cost, None)
+        .unwrap_or_revert_with(OrderBookError::TransferFailed);
+
+    let conditional_id = store_conditional(market_id, conditional);
+
+    runtime::ret(CLValue::from_t(conditional_id).unwrap_or_revert());
+}
+
+/// Place a conditional sell order
+/// Locks deposited tokens and escrows the keeper bounty from the payment_purse
+#[no_mangle]
+pub extern "C" fn place_conditional_sell() {
+    let caller = runtime::get_caller();
+    let conditional = read_conditional_args(caller, OrderSide::Sell);
+    let payment_purse: URef = runtime::get_named_arg("payment_purse");
+
+    // Lock tokens (reduce available balance)
+    let market_id = get_market_id(conditional.market);
+    lock_tokens(market_id, caller, conditional.amount);
+
+    let escrow_purse = get_escrow_purse(market_id);
+    system::transfer_from_purse_to_purse(payment_purse, escrow_purse, conditional.bounty, None)
+        .unwrap_or_revert_with(OrderBookError::TransferFailed);
+
+    let conditional_id = store_conditional(market_id, conditional);
+
+    runtime::ret(CLValue::from_t(conditional_id).unwrap_or_revert());
+}
+
+/// Cancel a conditional order that has not triggered yet
+/// Refunds its escrow and keeper bounty
+#[no_mangle]
+pub extern "C" fn cancel_conditional_order() {
+    let caller = runtime::get_caller();
+    let conditional_id: u64 = runtime::get_named_arg("conditional_id");
+
+    let mut conditional = read_conditional(conditional_id);
+
+    if conditional.owner != caller {
+        runtime::revert(OrderBookError::NotAuthorized);
+    }
+    if conditional.status != ConditionalStatus::Pending {
+        runtime::revert(OrderBookError::ConditionalNotPending);
+    }
+
+    let market_id = get_market_id(conditional.market);
+    if conditional.side == OrderSide::Buy {
+        let escrow = buy_refund(market_id, conditional.price, conditional.amount);
+        pay_cspr(market_id, caller, escrow);
+    } else {
+        let current_balance = get_token_balance(market_id, caller);
+        set_token_balance(market_id, caller, current_balance + conditional.amount);
+    }
+    pay_cspr(market_id, caller, conditional.bounty);
+
+    let mut pending = read_pending_conditionals(market_id);
+    pending.retain(|id| *id != conditional_id);
+    write_pending_conditionals(market_id, pending);
+
+    conditional.status = ConditionalStatus::Cancelled;
+    write_conditional(conditional_id, conditional);
+}
+
+/// Activate up to `max_orders` pending conditional orders in a market whose trigger was reached
+/// Anyone can call this, the caller earns each activated order's bounty.
+/// Returns the number of orders activated.
+#[no_mangle]
+pub extern "C" fn trigger_orders() {
+    let keeper = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let max_orders: u32 = runtime::get_named_arg("max_orders");
+
+    let market_id = get_market_id(market);
+    let last_price = get_last_price(market_id);
+    let mut pending = read_pending_conditionals(market_id);
+    let mut activated: Vec<u64> = Vec::new();
+
+    if last_price > U512::zero() {
+        for conditional_id in pending.iter().copied() {
+            if activated.len() as u32 >= max_orders {
+                break;
+            }
+
+            let mut conditional = read_conditional(conditional_id);
+            if !conditional.is_triggered_by(last_price) {
+                continue;
+            }
+
+            // Escrow is already held, place it as a resting limit order
+            conditional.order_id = place_order(
+                market,
+                conditional.owner,
+                conditional.side,
+                conditional.price,
+                conditional.amount,
+                OrderType::Limit,
+                0,
+                false,
+            );
+            pay_cspr(market_id, keeper, conditional.bounty);
+
+            conditional.status = ConditionalStatus::Triggered;
+            write_conditional(conditional_id, conditional);
+            activated.push(conditional_id);
+        }
+    }
+
+    let count = activated.len() as u32;
+    if count > 0 {
+        pending.retain(|id| !activated.contains(id));
+        write_pending_conditionals(market_id, pending);
+    }
+
+    runtime::ret(CLValue::from_t(count).unwrap_or_revert());
+}
+
+/// Get conditional order details
+#[no_mangle]
+pub extern "C" fn get_conditional_order() {
+    let conditional_id: u64 = runtime::get_named_arg("conditional_id");
+    let conditional = read_conditional(conditional_id);
+    runtime::ret(CLValue::from_t(conditional).unwrap_or_revert());
+}
+
+/// Set how the caller's new orders treat the caller's own resting orders
+#[no_mangle]
+pub extern "C" fn set_self_trade_mode() {
+    let caller = runtime::get_caller();
+    let mode: SelfTradeMode = runtime::get_named_arg("mode");
+
+    let modes_uref = get_uref(DICT_SELF_TRADE_MODES);
+    storage::dictionary_put(modes_uref, &caller.to_string(), mode);
+}
+
+/// Set the bounty escrowed per conditional order (admin only)
+/// Applies to orders placed after the change
+#[no_mangle]
+pub extern "C" fn set_keeper_bounty() {
+    only_admin();
+
+    let bounty: U512 = runtime::get_named_arg("bounty");
+    let bounty_uref = get_uref(KEY_KEEPER_BOUNTY);
+    storage::write(bounty_uref, bounty);
+}
+
+/// Set the maker and taker fees in basis points (admin only)
+/// A negative maker fee is a rebate and may not exceed the taker fee.
+#[no_mangle]
+pub extern "C" fn set_fee_schedule() {
+    only_admin();
+
+    let maker_fee_bps: i32 = runtime::get_named_arg("maker_fee_bps");
+    let taker_fee_bps: u32 = runtime::get_named_arg("taker_fee_bps");
+
+    let rebate_too_large = maker_fee_bps < 0 && maker_fee_bps.unsigned_abs() > taker_fee_bps;
+    if taker_fee_bps > MAX_FEE_BPS || maker_fee_bps > MAX_FEE_BPS as i32 || rebate_too_large {
+        runtime::revert(OrderBookError::InvalidFee);
+    }
+
+    storage::write(get_uref(KEY_MAKER_FEE_BPS), maker_fee_bps);
+    storage::write(get_uref(KEY_TAKER_FEE_BPS), taker_fee_bps);
+}
+
+/// Get the (maker, taker) fees in basis points
+#[no_mangle]
+pub extern "C" fn get_fee_schedule() {
+    let schedule = read_fee_schedule();
+    runtime::ret(CLValue::from_t(schedule).unwrap_or_revert());
+}
+
+/// Withdraw a market's collected fees to `recipient` (admin only)
+#[no_mangle]
+pub extern "C" fn withdraw_fees() {
+    only_admin();
+
+    let market: ContractHash = runtime::get_named_arg("market");
+    let recipient: AccountHash = runtime::get_named_arg("recipient");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    let market_id = get_market_id(market);
+    let fee_balance = get_fee_balance(market_id);
+    if amount > fee_balance {
+        runtime::revert(OrderBookError::InsufficientFunds);
+    }
+
+    set_fee_balance(market_id, fee_balance - amount);
+    pay_cspr(market_id, recipient, amount);
+}
+
+/// Cancel an open order
+#[no_mangle]
+pub extern "C" fn cancel_order() {
+    let caller = runtime::get_caller();
+    let order_id: u64 = runtime::get_named_arg("order_id");
+    cancel_open_order(caller, order_id);
+}
+
+/// Cancel all of the caller's open orders in a market and refund their escrow
+/// Returns the number of orders cancelled
+#[no_mangle]
+pub extern "C" fn cancel_all() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    get_market_id(market); // Reverts for an unknown market
+
+    let mut cancelled = 0u32;
+    for order_id in read_open_orders(caller) {
+        if read_order(order_id).market == market {
+            cancel_open_order(caller, order_id);
+            cancelled += 1;
+        }
+    }
+
+    runtime::ret(CLValue::from_t(cancelled).unwrap_or_revert());
+}
+
+/// Cancel and place several of the caller's orders in one market
+/// `cancels` are applied first, then `orders` are placed in list order as
+/// (side, price, amount), all with the same `order_type` and `expires_at`.
+/// Anything failing reverts the whole batch. Buys escrow from `payment_purse`,
+/// which is only read if the batch places a buy. Returns the IDs of the placed orders.
+#[no_mangle]
+pub extern "C" fn batch_orders() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let cancels: Vec<u64> = runtime::get_named_arg("cancels");
+    let orders: Vec<(OrderSide, U512, U512)> = runtime::get_named_arg("orders");
+    let order_type: OrderType = runtime::get_named_arg("order_type");
+    let expires_at: u64 = runtime::get_named_arg("expires_at"); // 0 = never
+
+    let market_id = get_market_id(market);
+
+    // Cancelling first frees escrow and tokens for the new orders
+    for order_id in cancels {
+        cancel_open_order(caller, order_id);
+    }
+
+    let mut payment_purse: Option<URef> = None;
+    let mut order_ids: Vec<u64> = Vec::new();
+    for (side, price, amount) in orders {
+        check_limit_order(market_id, price, amount);
+        if side == OrderSide::Buy {
+            let purse =
+                *payment_purse.get_or_insert_with(|| runtime::get_named_arg("payment_purse"));
+            escrow_buy(market_id, purse, price, amount);
+        } else {
+            lock_tokens(market_id, caller, amount);
+        }
+        let order_id = place_order(
+            market,
+            caller,
+            side,
+            price,
+            amount,
+            order_type,
+            expires_at,
+            false,
+        );
+        order_ids.push(order_id);
+    }
+
+    runtime::ret(CLValue::from_t(order_ids).unwrap_or_revert());
+}
+
+/// Change an open order's price and/or total quantity
+/// `amount` is the new total and must exceed what has already filled. A pure size
+/// reduction keeps the order's place in its level; any other change moves it to the
+/// back of its (new) level after matching it like a new order at the new price.
+/// The escrow difference is refunded or, for a buy that needs more, taken from
+/// `payment_purse`, which is only read in that case.
+#[no_mangle]
+pub extern "C" fn amend_order() {
+    let caller = runtime::get_caller();
+    let order_id: u64 = runtime::get_named_arg("order_id");
+    let new_price: U512 = runtime::get_named_arg("price");
+    let new_amount: U512 = runtime::get_named_arg("amount");
+
+    let mut order = read_order(order_id);
+
+    if order.owner != caller {
+        runtime::revert(OrderBookError::NotAuthorized);
+    }
+    if !order.is_open() {
+        runtime::revert(OrderBookError::OrderAlreadyFilled);
+    }
+    if order.is_expired(get_block_time()) {
+        runtime::revert(OrderBookError::InvalidExpiry);
+    }
+    if new_price == U512::zero() {
+        runtime::revert(OrderBookError::InvalidPrice);
+    }
+    if new_amount <= order.filled {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+
+    let market_id = get_market_id(order.market);
+    check_order_grid(market_id, new_price, new_amount);
+    let old_remaining = order.remaining();
+    let new_remaining = new_amount - order.filled;
+
+    // Settle the escrow difference
+    if order.side == OrderSide::Buy {
+        // Both sides round down: the book never pays out more than `buy_refund` of what
+        // is left, so an unchanged or smaller order never needs more escrow
+        let held = buy_refund(market_id, order.price, old_remaining);
+        let needed = buy_refund(market_id, new_price, new_remaining);
+        if needed > held {
+            let payment_purse: URef = runtime::get_named_arg("payment_purse");
+            let escrow_purse = get_escrow_purse(market_id);
+            system::transfer_from_purse_to_purse(payment_purse, escrow_purse, needed - held, None)
+                .unwrap_or_revert_with(OrderBookError::TransferFailed);
+        } else {
+            pay_cspr(market_id, caller, held - needed);
+        }
+    } else {
+        let user_balance = get_token_balance(market_id, caller);
+        if new_remaining > old_remaining {
+            let extra = new_remaining - old_remaining;
+            if user_balance < extra {
+                runtime::revert(OrderBookError::InsufficientFunds);
+            }
+            set_token_balance(market_id, caller, user_balance - extra);
+        } else {
+            set_token_balance(market_id, caller, user_balance + (old_remaining - new_remaining));
+        }
+    }
+
+    let keeps_priority = new_price == order.price && new_amount <= order.amount;
+    if keeps_priority {
+        order.amount = new_amount;
+        write_order(order_id, &order);
+        return;
+    }
+
+    // Leave the current level and come back in as a new order
+    remove_from_level(market_id, order.side, order.price, order_id);
+    order.price = new_price;
+    order.amount = new_amount;
+
+    let filled_before = order.filled;
+    let (_, spent) = match_order(market_id, order_id, &mut order, None);
+
+    // A buy escrowed at its new limit plus the fee cap, refund what it did not use
+    if order.side == OrderSide::Buy {
+        let escrow_used = buy_refund(market_id, new_price, order.filled - filled_before);
+        pay_cspr(market_id, caller, escrow_used - spent);
+    }
+
+    if order.status == OrderStatus::Cancelled {
+        // Self-trade prevention cancelled the rest
+        refund_unfilled(market_id, &order, order.remaining());
+        close_user_order(caller, order_id);
+    } else if order.is_open() {
+        add_to_level(market_id, order.side, new_price, order_id);
+    } else {
+        close_user_order(caller, order_id);
+    }
+
+    write_order(order_id, &order);
+}
+
+/// Refund and remove up to `max_orders` expired resting orders in a market
+/// Anyone can call this. Returns the number of orders purged.
+#[no_mangle]
+pub extern "C" fn purge_expired() {
+    let market: ContractHash = runtime::get_named_arg("market");
+    let max_orders: u32 = runtime::get_named_arg("max_orders");
+
+    let market_id = get_market_id(market);
+    let now = get_block_time();
+    let mut purged = 0u32;
+    let mut still_expiring: Vec<u64> = Vec::new();
+
+    for order_id in read_expiring_orders(market_id) {
+        let order = read_order(order_id);
+        if !order.is_open() {
+            // Filled or cancelled since, nothing left to purge
+            continue;
+        }
+        if purged >= max_orders || !order.is_expired(now) {
+            still_expiring.push(order_id);
+            continue;
+        }
+
+        remove_from_level(market_id, order.side, order.price, order_id);
+        expire_order(market_id, order_id, order);
+        purged += 1;
+    }
+
+    write_expiring_orders(market_id, still_expiring);
+
+    runtime::ret(CLValue::from_t(purged).unwrap_or_revert());
+}
+
+/// Get order details
+#[no_mangle]
+pub extern "C" fn get_order() {
+    let order_id: u64 = runtime::get_named_arg("order_id");
+    let order = read_order(order_id);
+    runtime::ret(CLValue::from_t(order).unwrap_or_revert());
+}
+
+/// Get a page of an account's order IDs, oldest first
+/// `offset` and `limit` select a window of the account's history, or of its open
+/// orders when `status` is open or partial. `status` then filters within that window,
+/// so a page can hold fewer than `limit` IDs; `None` returns every order.
+#[no_mangle]
+pub extern "C" fn get_user_orders() {
+    let account: AccountHash = runtime::get_named_arg("account");
+    let status: Option<OrderStatus> = runtime::get_named_arg("status");
+    let offset: u64 = runtime::get_named_arg("offset");
+    let limit: u64 = runtime::get_named_arg("limit");
+
+    let page_size = limit.min(MAX_PAGE_SIZE);
+    let window: Vec<u64> = match status {
+        // Open and partial orders are served from the smaller open index
+        Some(OrderStatus::Open) | Some(OrderStatus::Partial) => read_open_orders(account)
+            .into_iter()
+            .skip(offset as usize)
+            .take(page_size as usize)
+            .collect(),
+        // Only the window's history entries are read, whatever the history length
+        _ => {
+            let end = get_order_history_len(account).min(offset.saturating_add(page_size));
+            (offset..end)
+                .map(|index| read_order_history(account, index))
+                .collect()
+        }
+    };
+
+    let page: Vec<u64> = match status {
+        None => window,
+        Some(status) => window
+            .into_iter()
+            .filter(|order_id| read_order(*order_id).status == status)
+            .collect(),
+    };
+
+    runtime::ret(CLValue::from_t(page).unwrap_or_revert());
+}
+
+/// Get up to `limit` trades starting at trade ID `from_id`, oldest first
+/// Trade IDs start at 1 and have no gaps.
+#[no_mangle]
+pub extern "C" fn get_trades() {
+    let from_id: u64 = runtime::get_named_arg("from_id");
+    let limit: u64 = runtime::get_named_arg("limit");
+
+    let trades_uref = get_uref(DICT_TRADES);
+    let first_id = from_id.max(1);
+    let last_id = get_trade_counter();
+
+    let mut trades: Vec<Trade> = Vec::new();
+    let mut trade_id = first_id;
+    while trade_id <= last_id && (trades.len() as u64) < limit.min(MAX_PAGE_SIZE) {
+        let trade: Trade = storage::dictionary_get(trades_uref, &trade_id.to_string())
+            .unwrap_or_revert()
+            .unwrap_or_revert_with(OrderBookError::MissingKey);
+        trades.push(trade);
+        trade_id += 1;
+    }
+
+    runtime::ret(CLValue::from_t(trades).unwrap_or_revert());
+}
+
+/// Get a market's best bid price
+#[no_mangle]
+pub extern "C" fn get_best_bid() {
+    let market: ContractHash = runtime::get_named_arg("market");
+    let best_bid = read_best_bid(get_market_id(market));
+    runtime::ret(CLValue::from_t(best_bid).unwrap_or_revert());
+}
+
+/// Get a market's best ask price
+#[no_mangle]
+pub extern "C" fn get_best_ask() {
+    let market: ContractHash = runtime::get_named_arg("market");
+    let best_ask = read_best_ask(get_market_id(market));
+    runtime::ret(CLValue::from_t(best_ask).unwrap_or_revert());
+}
+
+/// Get up to `levels` price levels of one side, best first, as (price, open quantity)
+#[no_mangle]
+pub extern "C" fn get_depth() {
+    let market: ContractHash = runtime::get_named_arg("market");
+    let side: OrderSide = runtime::get_named_arg("side");
+    let levels: u64 = runtime::get_named_arg("levels");
+
+    let market_id = get_market_id(market);
+    let now = get_block_time();
+
+    let mut depth: Vec<(U512, U512)> = Vec::new();
+    for price in read_prices(market_id, side) {
+        if (depth.len() as u64) >= levels.min(MAX_PAGE_SIZE) {
+            break;
+        }
+        // Expired orders still sit in their level until matched or purged
+        let quantity = read_level(market_id, side, price)
+            .into_iter()
+            .map(read_order)
+            .filter(|order| order.is_open() && !order.is_expired(now))
+            .fold(U512::zero(), |total, order| total + order.remaining());
+        if !quantity.is_zero() {
+            depth.push((price, quantity));
+        }
+    }
+
+    runtime::ret(CLValue::from_t(depth).unwrap_or_revert());
+}
+
+/// Deposit a market's tokens to the order book (for selling)
+#[no_mangle]
+pub extern "C" fn deposit_tokens() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    if amount == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+    let market_id = get_market_id(market);
+
+    // Pull the tokens into the order book's custody (requires prior approval)
+    runtime::call_contract::<()>(
+        market,
+        CEP18_TRANSFER_FROM,
+        runtime_args! {
+            "owner" => Key::Account(caller),
+            "recipient" => get_self_key(),
+            "amount" => to_token_amount(amount),
+        },
+    );
+
+    let current_balance = get_token_balance(market_id, caller);
+    set_token_balance(market_id, caller, current_balance + amount);
+}
+
+/// Withdraw a market's tokens from the order book
+#[no_mangle]
+pub extern "C" fn withdraw_tokens() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    if amount == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+
+    let market_id = get_market_id(market);
+    let current_balance = get_token_balance(market_id, caller);
+    if current_balance < amount {
+        runtime::revert(OrderBookError::InsufficientFunds);
+    }
+
+    // Debit before paying out
+    set_token_balance(market_id, caller, current_balance - amount);
+
+    runtime::call_contract::<()>(
+        market,
+        CEP18_TRANSFER,
+        runtime_args! {
+            "recipient" => Key::Account(caller),
+            "amount" => to_token_amount(amount),
+        },
+    );
+}
+
+/// Withdraw CSPR credited to the caller in a market
+#[no_mangle]
+pub extern "C" fn withdraw_cspr() {
+    let caller = runtime::get_caller();
+    let market: ContractHash = runtime::get_named_arg("market");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    if amount == U512::zero() {
+        runtime::revert(OrderBookError::InvalidAmount);
+    }
+
+    let market_id = get_market_id(market);
+    let current_balance = get_cspr_balance(market_id, caller);
+    if current_balance < amount {
+        runtime::revert(OrderBookError::InsufficientFunds);
+    }
+
+    // Debit before paying out
+    set_cspr_balance(market_id, caller, current_balance - amount);
+    pay_cspr(market_id, caller, amount);
+}
+
+// ============================================================================
+// Contract Installation
+// ============================================================================
+
+fn build_entry_points() -> EntryPoints {
+    let mut entry_points = EntryPoints::new();
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_CREATE_MARKET,
+        vec![
+            Parameter::new("token", CLType::ByteArray(32)),
+            Parameter::new("decimals", CLType::Option(Box::new(CLType::U8))),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_SET_MARKET_PARAMS,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("tick_size", CLType::U512),
+            Parameter::new("lot_size", CLType::U512),
+            Parameter::new("min_notional", CLType::U512),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_SET_MARKET_AMM,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("amm", CLType::Option(Box::new(CLType::ByteArray(32)))),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_SET_LAUNCHPAD,
+        vec![Parameter::new("launchpad", CLType::ByteArray(32))],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
         EP_PLACE_BUY_ORDER,
         vec![
+            Parameter::new("market", CLType::ByteArray(32)),
             Parameter::new("price", CLType::U512),
             Parameter::new("amount", CLType::U512),
             Parameter::new("payment_purse", CLType::URef),
+            Parameter::new("order_type", CLType::U8),
+            Parameter::new("expires_at", CLType::U64),
+            Parameter::new("route_to_amm", CLType::Bool),
         ],
         CLType::U64,
         EntryPointAccess::Public,
@@ -418,14 +2572,48 @@ fn build_entry_points() -> EntryPoints {
     entry_points.add_entry_point(EntryPoint::new(
         EP_PLACE_SELL_ORDER,
         vec![
+            Parameter::new("market", CLType::ByteArray(32)),
             Parameter::new("price", CLType::U512),
             Parameter::new("amount", CLType::U512),
+            Parameter::new("order_type", CLType::U8),
+            Parameter::new("expires_at", CLType::U64),
         ],
         CLType::U64,
         EntryPointAccess::Public,
         EntryPointType::Called,
     ));
 
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_PLACE_MARKET_BUY,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("amount", CLType::U512),
+            Parameter::new("worst_price", CLType::U512),
+            Parameter::new("max_spend", CLType::U512),
+            Parameter::new("payment_purse", CLType::URef),
+            Parameter::new("route_to_amm", CLType::Bool),
+        ],
+        CLType::Tuple3([
+            Box::new(CLType::U512),
+            Box::new(CLType::U512),
+            Box::new(CLType::U512),
+        ]),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_PLACE_MARKET_SELL,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("amount", CLType::U512),
+            Parameter::new("worst_price", CLType::U512),
+        ],
+        CLType::Tuple2([Box::new(CLType::U512), Box::new(CLType::U512)]),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
     entry_points.add_entry_point(EntryPoint::new(
         EP_CANCEL_ORDER,
         vec![Parameter::new("order_id", CLType::U64)],
@@ -434,17 +2622,71 @@ fn build_entry_points() -> EntryPoints {
         EntryPointType::Called,
     ));
 
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_CANCEL_ALL,
+        vec![Parameter::new("market", CLType::ByteArray(32))],
+        CLType::U32,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_BATCH_ORDERS,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("cancels", CLType::List(Box::new(CLType::U64))),
+            Parameter::new(
+                "orders",
+                CLType::List(Box::new(CLType::Tuple3([
+                    Box::new(CLType::U8),
+                    Box::new(CLType::U512),
+                    Box::new(CLType::U512),
+                ]))),
+            ),
+            Parameter::new("order_type", CLType::U8),
+            Parameter::new("expires_at", CLType::U64),
+            Parameter::new("payment_purse", CLType::URef),
+        ],
+        CLType::List(Box::new(CLType::U64)),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_AMEND_ORDER,
+        vec![
+            Parameter::new("order_id", CLType::U64),
+            Parameter::new("price", CLType::U512),
+            Parameter::new("amount", CLType::U512),
+            Parameter::new("payment_purse", CLType::URef),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_PURGE_EXPIRED,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("max_orders", CLType::U32),
+        ],
+        CLType::U32,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
     entry_points.add_entry_point(EntryPoint::new(
         EP_GET_ORDER,
         vec![Parameter::new("order_id", CLType::U64)],
-        CLType::String,
+        CLType::Any,
         EntryPointAccess::Public,
         EntryPointType::Called,
     ));
 
     entry_points.add_entry_point(EntryPoint::new(
         EP_GET_BEST_BID,
-        vec![],
+        vec![Parameter::new("market", CLType::ByteArray(32))],
         CLType::U512,
         EntryPointAccess::Public,
         EntryPointType::Called,
@@ -452,15 +2694,161 @@ fn build_entry_points() -> EntryPoints {
 
     entry_points.add_entry_point(EntryPoint::new(
         EP_GET_BEST_ASK,
-        vec![],
+        vec![Parameter::new("market", CLType::ByteArray(32))],
         CLType::U512,
         EntryPointAccess::Public,
         EntryPointType::Called,
     ));
 
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_DEPTH,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("side", CLType::U8),
+            Parameter::new("levels", CLType::U64),
+        ],
+        CLType::List(Box::new(CLType::Tuple2([
+            Box::new(CLType::U512),
+            Box::new(CLType::U512),
+        ]))),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_USER_ORDERS,
+        vec![
+            Parameter::new("account", CLType::ByteArray(32)),
+            Parameter::new("status", CLType::Option(Box::new(CLType::U8))),
+            Parameter::new("offset", CLType::U64),
+            Parameter::new("limit", CLType::U64),
+        ],
+        CLType::List(Box::new(CLType::U64)),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_TRADES,
+        vec![
+            Parameter::new("from_id", CLType::U64),
+            Parameter::new("limit", CLType::U64),
+        ],
+        CLType::List(Box::new(CLType::Any)),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_PLACE_CONDITIONAL_BUY,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("trigger_price", CLType::U512),
+            Parameter::new("trigger_above", CLType::Bool),
+            Parameter::new("price", CLType::U512),
+            Parameter::new("amount", CLType::U512),
+            Parameter::new("payment_purse", CLType::URef),
+        ],
+        CLType::U64,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_PLACE_CONDITIONAL_SELL,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("trigger_price", CLType::U512),
+            Parameter::new("trigger_above", CLType::Bool),
+            Parameter::new("price", CLType::U512),
+            Parameter::new("amount", CLType::U512),
+            Parameter::new("payment_purse", CLType::URef),
+        ],
+        CLType::U64,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_CANCEL_CONDITIONAL_ORDER,
+        vec![Parameter::new("conditional_id", CLType::U64)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_TRIGGER_ORDERS,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("max_orders", CLType::U32),
+        ],
+        CLType::U32,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_CONDITIONAL_ORDER,
+        vec![Parameter::new("conditional_id", CLType::U64)],
+        CLType::Any,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_SET_SELF_TRADE_MODE,
+        vec![Parameter::new("mode", CLType::U8)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_SET_KEEPER_BOUNTY,
+        vec![Parameter::new("bounty", CLType::U512)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_SET_FEE_SCHEDULE,
+        vec![
+            Parameter::new("maker_fee_bps", CLType::I32),
+            Parameter::new("taker_fee_bps", CLType::U32),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_GET_FEE_SCHEDULE,
+        vec![],
+        CLType::Tuple2([Box::new(CLType::I32), Box::new(CLType::U32)]),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_WITHDRAW_FEES,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("recipient", CLType::ByteArray(32)),
+            Parameter::new("amount", CLType::U512),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
     entry_points.add_entry_point(EntryPoint::new(
         EP_DEPOSIT_TOKENS,
-        vec![Parameter::new("amount", CLType::U512)],
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("amount", CLType::U512),
+        ],
         CLType::Unit,
         EntryPointAccess::Public,
         EntryPointType::Called,
@@ -468,7 +2856,21 @@ fn build_entry_points() -> EntryPoints {
 
     entry_points.add_entry_point(EntryPoint::new(
         EP_WITHDRAW_TOKENS,
-        vec![Parameter::new("amount", CLType::U512)],
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("amount", CLType::U512),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+    ));
+
+    entry_points.add_entry_point(EntryPoint::new(
+        EP_WITHDRAW_CSPR,
+        vec![
+            Parameter::new("market", CLType::ByteArray(32)),
+            Parameter::new("amount", CLType::U512),
+        ],
         CLType::Unit,
         EntryPointAccess::Public,
         EntryPointType::Called,
@@ -481,30 +2883,66 @@ fn build_entry_points() -> EntryPoints {
 pub extern "C" fn call() {
     let admin: AccountHash = runtime::get_named_arg("admin");
 
-    // Create purse for CSPR escrow
-    let cspr_purse = system::create_purse();
-
     // Create dictionaries
+    let markets_uref = storage::new_dictionary(DICT_MARKETS).unwrap_or_revert();
+    let market_state_uref = storage::new_dictionary(DICT_MARKET_STATE).unwrap_or_revert();
     let orders_uref = storage::new_dictionary(DICT_ORDERS).unwrap_or_revert();
     let user_orders_uref = storage::new_dictionary(DICT_USER_ORDERS).unwrap_or_revert();
+    let user_open_orders_uref = storage::new_dictionary(DICT_USER_OPEN_ORDERS).unwrap_or_revert();
     let token_balances_uref = storage::new_dictionary(DICT_TOKEN_BALANCES).unwrap_or_revert();
+    let cspr_balances_uref = storage::new_dictionary(DICT_CSPR_BALANCES).unwrap_or_revert();
+    let price_levels_uref = storage::new_dictionary(DICT_PRICE_LEVELS).unwrap_or_revert();
+    let conditional_orders_uref =
+        storage::new_dictionary(DICT_CONDITIONAL_ORDERS).unwrap_or_revert();
+    let trades_uref = storage::new_dictionary(DICT_TRADES).unwrap_or_revert();
+    let self_trade_modes_uref = storage::new_dictionary(DICT_SELF_TRADE_MODES).unwrap_or_revert();
 
     // Create storage for parameters
     let admin_uref = storage::new_uref(admin);
+    let launchpad_uref = storage::new_uref(AccountHash::default());
     let counter_uref = storage::new_uref(0u64);
-    let best_bid_uref = storage::new_uref(U512::zero());
-    let best_ask_uref = storage::new_uref(U512::MAX);
+    let market_counter_uref = storage::new_uref(0u64);
+    let keeper_bounty_uref = storage::new_uref(U512::from(DEFAULT_KEEPER_BOUNTY));
+    let conditional_counter_uref = storage::new_uref(0u64);
+    let trade_counter_uref = storage::new_uref(0u64);
+    let maker_fee_uref = storage::new_uref(0i32);
+    let taker_fee_uref = storage::new_uref(0u32);
+
+    // Filled in once the contract package exists
+    let self_package_uref = storage::new_uref(Option::<ContractPackageHash>::None);
 
     // Build named keys
     let mut named_keys = NamedKeys::new();
     named_keys.insert(KEY_ADMIN.to_string(), admin_uref.into());
-    named_keys.insert(KEY_CSPR_PURSE.to_string(), cspr_purse.into());
+    named_keys.insert(KEY_LAUNCHPAD.to_string(), launchpad_uref.into());
     named_keys.insert(KEY_ORDER_COUNTER.to_string(), counter_uref.into());
-    named_keys.insert(KEY_BEST_BID.to_string(), best_bid_uref.into());
-    named_keys.insert(KEY_BEST_ASK.to_string(), best_ask_uref.into());
+    named_keys.insert(KEY_MARKET_COUNTER.to_string(), market_counter_uref.into());
+    named_keys.insert(KEY_KEEPER_BOUNTY.to_string(), keeper_bounty_uref.into());
+    named_keys.insert(
+        KEY_CONDITIONAL_COUNTER.to_string(),
+        conditional_counter_uref.into(),
+    );
+    named_keys.insert(KEY_SELF_PACKAGE.to_string(), self_package_uref.into());
+    named_keys.insert(KEY_TRADE_COUNTER.to_string(), trade_counter_uref.into());
+    named_keys.insert(KEY_MAKER_FEE_BPS.to_string(), maker_fee_uref.into());
+    named_keys.insert(KEY_TAKER_FEE_BPS.to_string(), taker_fee_uref.into());
+    named_keys.insert(DICT_MARKETS.to_string(), markets_uref.into());
+    named_keys.insert(DICT_MARKET_STATE.to_string(), market_state_uref.into());
     named_keys.insert(DICT_ORDERS.to_string(), orders_uref.into());
     named_keys.insert(DICT_USER_ORDERS.to_string(), user_orders_uref.into());
+    named_keys.insert(DICT_USER_OPEN_ORDERS.to_string(), user_open_orders_uref.into());
     named_keys.insert(DICT_TOKEN_BALANCES.to_string(), token_balances_uref.into());
+    named_keys.insert(DICT_CSPR_BALANCES.to_string(), cspr_balances_uref.into());
+    named_keys.insert(DICT_PRICE_LEVELS.to_string(), price_levels_uref.into());
+    named_keys.insert(
+        DICT_CONDITIONAL_ORDERS.to_string(),
+        conditional_orders_uref.into(),
+    );
+    named_keys.insert(DICT_TRADES.to_string(), trades_uref.into());
+    named_keys.insert(
+        DICT_SELF_TRADE_MODES.to_string(),
+        self_trade_modes_uref.into(),
+    );
 
     // Create entry points
     let entry_points = build_entry_points();
@@ -518,6 +2956,13 @@ pub extern "C" fn call() {
         None,
     );
 
+    // Let the contract know its package hash, which holds its CEP-18 balances
+    let package_hash = runtime::get_key(CONTRACT_PACKAGE_KEY)
+        .and_then(Key::into_hash_addr)
+        .map(ContractPackageHash::new)
+        .unwrap_or_revert_with(OrderBookError::MissingKey);
+    storage::write(self_package_uref, Some(package_hash));
+
     // Store the contract hash
     runtime::put_key(CONTRACT_HASH_KEY, contract_hash.into());
 }
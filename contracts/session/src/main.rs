@@ -5,8 +5,9 @@
 //!
 //! # Arguments
 //! - `vault_contract_hash`: The contract hash of the Token Vault
-//! - `order_id`: Unique identifier for the order
+//! - `order_id`: Identifier for the order, unique per owner (max 32 chars)
 //! - `amount`: Amount of CSPR to lock (in motes)
+//! - `expires_at`: Block time (ms) after which the lock can be expired, 0 = never
 
 #![no_std]
 #![no_main]
@@ -26,6 +27,7 @@ pub extern "C" fn call() {
     let vault_contract_hash: ContractHash = runtime::get_named_arg("vault_contract_hash");
     let order_id: String = runtime::get_named_arg("order_id");
     let amount: U512 = runtime::get_named_arg("amount");
+    let expires_at: u64 = runtime::get_named_arg("expires_at");
 
     // Get caller's main purse
     let main_purse: URef = account::get_main_purse();
@@ -42,6 +44,7 @@ pub extern "C" fn call() {
         "order_id" => order_id,
         "amount" => amount,
         "payment_purse" => temp_purse,
+        "expires_at" => expires_at,
     };
 
     runtime::call_contract::<()>(vault_contract_hash, "lock_cspr", args);
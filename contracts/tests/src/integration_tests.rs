@@ -2,32 +2,49 @@ fn main() {
     panic!("Execute \"cargo test\" to test the contract, not \"cargo run\".");
 }
 
+#[cfg(test)]
+mod orderbook_tests;
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
     use casper_engine_test_support::{
-        DeployItemBuilder, ExecuteRequestBuilder, LmdbWasmTestBuilder, ARG_AMOUNT,
-        DEFAULT_ACCOUNT_ADDR, DEFAULT_PAYMENT, LOCAL_GENESIS_REQUEST,
+        DeployItemBuilder, ExecuteRequestBuilder, LmdbWasmTestBuilder, TransferRequestBuilder,
+        ARG_AMOUNT, DEFAULT_ACCOUNT_ADDR, DEFAULT_PAYMENT, LOCAL_GENESIS_REQUEST,
     };
     use casper_execution_engine::{engine_state::Error, execution::ExecError};
     use casper_types::{
-        account::AccountHash, runtime_args, ApiError, Key, RuntimeArgs, URef, U512,
+        account::AccountHash, bytesrepr::FromBytes, runtime_args, AccessRights, ApiError,
+        BlockTime, CLTyped, Key, RuntimeArgs, URef, U512,
     };
 
     // Contract constants
     const CONTRACT_WASM: &str = "contract.wasm";
+    const CONTRACT_HASH_KEY: &str = "token_vault_hash";
     const KEY_ADMIN: &str = "admin";
     const KEY_CSPR_PURSE: &str = "cspr_purse";
-    const DICT_LOCKED_CSPR: &str = "locked_cspr";
 
     // Error codes from contract
     const ERROR_INSUFFICIENT_BALANCE: u16 = 1;
     const ERROR_ORDER_NOT_FOUND: u16 = 2;
-    const ERROR_NOT_ORDER_OWNER: u16 = 3;
     const ERROR_NOT_AUTHORIZED: u16 = 4;
     const ERROR_ALREADY_LOCKED: u16 = 5;
     const ERROR_INVALID_AMOUNT: u16 = 6;
+    const ERROR_EXCEEDS_SURPLUS: u16 = 11;
+    const ERROR_INVALID_ORDER_ID: u16 = 12;
+    const ERROR_INVALID_EXPIRY: u16 = 13;
+    const ERROR_PAUSED: u16 = 17;
+    const ERROR_NOT_PAUSED: u16 = 18;
+    const ERROR_EMERGENCY_EXIT_ACTIVE: u16 = 19;
+    const ERROR_EMERGENCY_EXIT_NOT_READY: u16 = 20;
+
+    // Lock statuses as stored by the vault
+    const LOCK_OPEN: u8 = 0;
+    const LOCK_CANCELLED: u8 = 3;
+
+    // Mirrors the vault's EMERGENCY_EXIT_DELAY_MS
+    const EMERGENCY_EXIT_DELAY_MS: u64 = 7 * 24 * 60 * 60 * 1000;
 
     fn deploy_contract(builder: &mut LmdbWasmTestBuilder) {
         let session_code = PathBuf::from(CONTRACT_WASM);
@@ -48,6 +65,135 @@ mod tests {
         builder.exec(execute_request).commit().expect_success();
     }
 
+    fn call_vault(
+        builder: &mut LmdbWasmTestBuilder,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> &mut LmdbWasmTestBuilder {
+        let execute_request = ExecuteRequestBuilder::contract_call_by_name(
+            *DEFAULT_ACCOUNT_ADDR,
+            CONTRACT_HASH_KEY,
+            entry_point,
+            args,
+        )
+        .build();
+        builder.exec(execute_request).commit()
+    }
+
+    fn call_vault_at(
+        builder: &mut LmdbWasmTestBuilder,
+        entry_point: &str,
+        args: RuntimeArgs,
+        block_time: u64,
+    ) -> &mut LmdbWasmTestBuilder {
+        let execute_request = ExecuteRequestBuilder::contract_call_by_name(
+            *DEFAULT_ACCOUNT_ADDR,
+            CONTRACT_HASH_KEY,
+            entry_point,
+            args,
+        )
+        .with_block_time(BlockTime::new(block_time))
+        .build();
+        builder.exec(execute_request).commit()
+    }
+
+    pub(crate) fn last_return<T: CLTyped + FromBytes>(builder: &LmdbWasmTestBuilder) -> T {
+        builder
+            .get_last_exec_result()
+            .expect("must have execution result")
+            .ret()
+            .expect("must return a value")
+            .clone()
+            .into_t()
+            .expect("return value must have the expected type")
+    }
+
+    pub(crate) fn assert_user_error(builder: &LmdbWasmTestBuilder, code: u16) {
+        let actual_error = builder.get_error().expect("must have error");
+        assert!(
+            matches!(
+                actual_error,
+                Error::Exec(ExecError::Revert(ApiError::User(c))) if c == code
+            ),
+            "Expected user error {}, received {:?}",
+            code,
+            actual_error
+        );
+    }
+
+    pub(crate) fn main_purse(builder: &LmdbWasmTestBuilder, account: AccountHash) -> URef {
+        builder
+            .get_expected_addressable_entity_by_account_hash(account)
+            .main_purse()
+    }
+
+    fn vault_purse(builder: &LmdbWasmTestBuilder) -> URef {
+        let contract = builder
+            .query(
+                None,
+                Key::Account(*DEFAULT_ACCOUNT_ADDR),
+                &[CONTRACT_HASH_KEY.to_string()],
+            )
+            .expect("should have vault contract");
+        contract
+            .as_contract()
+            .expect("should be a contract")
+            .named_keys()
+            .get(KEY_CSPR_PURSE)
+            .and_then(Key::as_uref)
+            .copied()
+            .expect("should have vault purse")
+    }
+
+    /// Lock `amount` from the default account's main purse
+    fn lock_cspr(builder: &mut LmdbWasmTestBuilder, order_id: &str, amount: u64) {
+        let payment_purse = main_purse(builder, *DEFAULT_ACCOUNT_ADDR);
+        call_vault(
+            builder,
+            "lock_cspr",
+            runtime_args! {
+                "order_id" => order_id.to_string(),
+                "amount" => U512::from(amount),
+                "payment_purse" => payment_purse,
+                "expires_at" => 0u64,
+            },
+        )
+        .expect_success();
+    }
+
+    /// (original_amount, remaining_amount, status) of one of the default account's locks
+    fn lock_record(builder: &mut LmdbWasmTestBuilder, order_id: &str) -> (U512, U512, u8) {
+        call_vault(
+            builder,
+            "get_lock",
+            runtime_args! {
+                "owner" => *DEFAULT_ACCOUNT_ADDR,
+                "order_id" => order_id.to_string(),
+            },
+        )
+        .expect_success();
+        let record = builder
+            .get_last_exec_result()
+            .expect("must have execution result")
+            .ret()
+            .expect("must return a value")
+            .inner_bytes()
+            .clone();
+
+        // Owner hash first, then the amounts and status
+        let remainder = &record[32..];
+        let (original_amount, remainder) = U512::from_bytes(remainder).unwrap();
+        let (remaining_amount, remainder) = U512::from_bytes(remainder).unwrap();
+        let (status, _) = u8::from_bytes(remainder).unwrap();
+        (original_amount, remaining_amount, status)
+    }
+
+    /// Returns (purse_balance, total_locked, surplus)
+    fn reconcile(builder: &mut LmdbWasmTestBuilder) -> (U512, U512, U512) {
+        call_vault(builder, "reconcile", RuntimeArgs::new()).expect_success();
+        last_return(builder)
+    }
+
     #[test]
     fn should_deploy_contract_with_admin() {
         let mut builder = LmdbWasmTestBuilder::default();
@@ -131,7 +277,8 @@ mod tests {
         // This test documents that cancel_order can only be called by the order owner
         // Expected behavior:
         // 1. User A locks CSPR for order "order-1"
-        // 2. User B tries to cancel "order-1" -> should fail with NOT_ORDER_OWNER
+        // 2. User B tries to cancel "order-1" -> only reaches B's own namespace,
+        //    so it fails with ORDER_NOT_FOUND
         // 3. User A cancels "order-1" -> should succeed and refund CSPR
 
         let mut builder = LmdbWasmTestBuilder::default();
@@ -170,4 +317,389 @@ mod tests {
 
         assert!(true, "Contract deployed for set_order_book testing");
     }
+
+    #[test]
+    fn should_not_sweep_more_than_surplus() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        // Fresh vault has an empty purse, so there is no surplus to sweep
+        call_vault(
+            &mut builder,
+            "sweep_surplus",
+            runtime_args! {
+                "recipient" => *DEFAULT_ACCOUNT_ADDR,
+                "amount" => U512::from(1u64),
+            },
+        )
+        .expect_failure();
+
+        assert_user_error(&builder, ERROR_EXCEEDS_SURPLUS);
+    }
+
+    #[test]
+    fn should_reconcile_empty_vault() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        call_vault(&mut builder, "reconcile", RuntimeArgs::new()).expect_success();
+
+        let (balance, total_locked, surplus): (U512, U512, U512) = last_return(&builder);
+        assert_eq!(balance, U512::zero());
+        assert_eq!(total_locked, U512::zero());
+        assert_eq!(surplus, U512::zero());
+    }
+
+    #[test]
+    fn should_track_total_locked_through_lock_unlock_and_cancel() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        lock_cspr(&mut builder, "order-1", 5_000);
+        lock_cspr(&mut builder, "order-2", 3_000);
+        let expected = (U512::from(8_000), U512::from(8_000), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+
+        // Admin settles part of the first lock
+        call_vault(
+            &mut builder,
+            "unlock_cspr",
+            runtime_args! {
+                "owner" => *DEFAULT_ACCOUNT_ADDR,
+                "order_id" => "order-1".to_string(),
+                "recipient" => *DEFAULT_ACCOUNT_ADDR,
+                "amount" => U512::from(2_000),
+            },
+        )
+        .expect_success();
+        let expected = (U512::from(6_000), U512::from(6_000), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+
+        call_vault(
+            &mut builder,
+            "cancel_order",
+            runtime_args! {
+                "order_id" => "order-2".to_string(),
+            },
+        )
+        .expect_success();
+        let expected = (U512::from(3_000), U512::from(3_000), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+    }
+
+    #[test]
+    fn should_sweep_surplus_but_not_locked_funds() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        lock_cspr(&mut builder, "order-1", 5_000);
+
+        // CSPR sent straight to the vault purse backs no lock
+        let transfer = TransferRequestBuilder::new(1_000u64, vault_purse(&builder)).build();
+        builder.transfer_and_commit(transfer).expect_success();
+        let expected = (U512::from(6_000), U512::from(5_000), U512::from(1_000));
+        assert_eq!(reconcile(&mut builder), expected);
+
+        call_vault(
+            &mut builder,
+            "sweep_surplus",
+            runtime_args! {
+                "recipient" => *DEFAULT_ACCOUNT_ADDR,
+                "amount" => U512::from(1_001),
+            },
+        )
+        .expect_failure();
+        assert_user_error(&builder, ERROR_EXCEEDS_SURPLUS);
+
+        call_vault(
+            &mut builder,
+            "sweep_surplus",
+            runtime_args! {
+                "recipient" => *DEFAULT_ACCOUNT_ADDR,
+                "amount" => U512::from(1_000),
+            },
+        )
+        .expect_success();
+        let expected = (U512::from(5_000), U512::from(5_000), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+    }
+
+    #[test]
+    fn should_reject_oversized_order_id() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        // Order IDs share the dictionary key with the owner hash, so they are capped
+        call_vault(
+            &mut builder,
+            "lock_cspr",
+            runtime_args! {
+                "order_id" => "x".repeat(33),
+                "amount" => U512::from(1u64),
+                "payment_purse" => URef::new([0u8; 32], AccessRights::READ_ADD_WRITE),
+                "expires_at" => 0u64,
+            },
+        )
+        .expect_failure();
+
+        assert_user_error(&builder, ERROR_INVALID_ORDER_ID);
+    }
+
+    #[test]
+    fn should_reject_expiry_in_the_past() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        // Expiry 1 ms is already behind the block time of the call
+        call_vault_at(
+            &mut builder,
+            "lock_cspr",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+                "amount" => U512::from(1u64),
+                "payment_purse" => URef::new([0u8; 32], AccessRights::READ_ADD_WRITE),
+                "expires_at" => 1u64,
+            },
+            1_000,
+        )
+        .expect_failure();
+
+        assert_user_error(&builder, ERROR_INVALID_EXPIRY);
+    }
+
+    #[test]
+    fn should_list_no_open_locks_for_new_owner() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        call_vault(
+            &mut builder,
+            "get_open_locks",
+            runtime_args! {
+                "owner" => *DEFAULT_ACCOUNT_ADDR,
+            },
+        )
+        .expect_success();
+
+        let open_locks: Vec<String> = last_return(&builder);
+        assert!(open_locks.is_empty(), "new owner should have no open locks");
+    }
+
+    #[test]
+    fn should_block_lock_cspr_while_paused() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        call_vault(&mut builder, "pause", RuntimeArgs::new()).expect_success();
+
+        call_vault(
+            &mut builder,
+            "lock_cspr",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+                "amount" => U512::from(1u64),
+                "payment_purse" => URef::new([0u8; 32], AccessRights::READ_ADD_WRITE),
+                "expires_at" => 0u64,
+            },
+        )
+        .expect_failure();
+
+        assert_user_error(&builder, ERROR_PAUSED);
+    }
+
+    #[test]
+    fn should_let_owner_cancel_and_get_refund_while_paused() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        lock_cspr(&mut builder, "order-1", 5_000);
+        call_vault(&mut builder, "pause", RuntimeArgs::new()).expect_success();
+
+        call_vault(
+            &mut builder,
+            "cancel_order",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+            },
+        )
+        .expect_success();
+
+        // The refund left the vault purse
+        let expected = (U512::zero(), U512::zero(), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+        let expected = (U512::from(5_000), U512::zero(), LOCK_CANCELLED);
+        assert_eq!(lock_record(&mut builder, "order-1"), expected);
+    }
+
+    #[test]
+    fn should_withdraw_all_locks_in_emergency_exit() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        lock_cspr(&mut builder, "order-1", 5_000);
+        lock_cspr(&mut builder, "order-2", 3_000);
+        let paused_at = 1_000;
+        call_vault_at(&mut builder, "pause", RuntimeArgs::new(), paused_at).expect_success();
+
+        let ready_at = paused_at + EMERGENCY_EXIT_DELAY_MS;
+        call_vault_at(
+            &mut builder,
+            "trigger_emergency_exit",
+            RuntimeArgs::new(),
+            ready_at - 1,
+        )
+        .expect_failure();
+        assert_user_error(&builder, ERROR_EMERGENCY_EXIT_NOT_READY);
+
+        call_vault_at(
+            &mut builder,
+            "trigger_emergency_exit",
+            RuntimeArgs::new(),
+            ready_at,
+        )
+        .expect_success();
+        call_vault_at(
+            &mut builder,
+            "emergency_withdraw",
+            RuntimeArgs::new(),
+            ready_at,
+        )
+        .expect_success();
+
+        let expected = (U512::zero(), U512::zero(), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+        let expected = (U512::from(5_000), U512::zero(), LOCK_CANCELLED);
+        assert_eq!(lock_record(&mut builder, "order-1"), expected);
+        let expected = (U512::from(3_000), U512::zero(), LOCK_CANCELLED);
+        assert_eq!(lock_record(&mut builder, "order-2"), expected);
+
+        // Emergency exit is final
+        call_vault(&mut builder, "unpause", RuntimeArgs::new()).expect_failure();
+        assert_user_error(&builder, ERROR_EMERGENCY_EXIT_ACTIVE);
+    }
+
+    #[test]
+    fn should_not_trigger_emergency_exit_when_not_paused() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        call_vault(&mut builder, "trigger_emergency_exit", RuntimeArgs::new()).expect_failure();
+
+        assert_user_error(&builder, ERROR_NOT_PAUSED);
+    }
+
+    #[test]
+    fn should_not_reduce_unknown_lock() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        call_vault(
+            &mut builder,
+            "reduce_lock",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+                "amount" => U512::from(1u64),
+            },
+        )
+        .expect_failure();
+
+        assert_user_error(&builder, ERROR_ORDER_NOT_FOUND);
+    }
+
+    #[test]
+    fn should_top_up_and_reduce_lock() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        lock_cspr(&mut builder, "order-1", 5_000);
+
+        let payment_purse = main_purse(&builder, *DEFAULT_ACCOUNT_ADDR);
+        call_vault(
+            &mut builder,
+            "increase_lock",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+                "amount" => U512::from(2_000),
+                "payment_purse" => payment_purse,
+            },
+        )
+        .expect_success();
+        let expected = (U512::from(7_000), U512::from(7_000), LOCK_OPEN);
+        assert_eq!(lock_record(&mut builder, "order-1"), expected);
+        assert_eq!(reconcile(&mut builder).1, U512::from(7_000));
+
+        // A partial reduce refunds without touching the deposited total
+        call_vault(
+            &mut builder,
+            "reduce_lock",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+                "amount" => U512::from(3_000),
+            },
+        )
+        .expect_success();
+        let expected = (U512::from(7_000), U512::from(4_000), LOCK_OPEN);
+        assert_eq!(lock_record(&mut builder, "order-1"), expected);
+        let expected = (U512::from(4_000), U512::from(4_000), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+    }
+
+    #[test]
+    fn should_cancel_lock_reduced_to_zero() {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+        deploy_contract(&mut builder);
+
+        lock_cspr(&mut builder, "order-1", 5_000);
+
+        call_vault(
+            &mut builder,
+            "reduce_lock",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+                "amount" => U512::from(5_001),
+            },
+        )
+        .expect_failure();
+        assert_user_error(&builder, ERROR_INSUFFICIENT_BALANCE);
+
+        call_vault(
+            &mut builder,
+            "reduce_lock",
+            runtime_args! {
+                "order_id" => "order-1".to_string(),
+                "amount" => U512::from(5_000),
+            },
+        )
+        .expect_success();
+
+        let expected = (U512::from(5_000), U512::zero(), LOCK_CANCELLED);
+        assert_eq!(lock_record(&mut builder, "order-1"), expected);
+        let expected = (U512::zero(), U512::zero(), U512::zero());
+        assert_eq!(reconcile(&mut builder), expected);
+
+        call_vault(
+            &mut builder,
+            "get_open_locks",
+            runtime_args! {
+                "owner" => *DEFAULT_ACCOUNT_ADDR,
+            },
+        )
+        .expect_success();
+        let open_locks: Vec<String> = last_return(&builder);
+        assert!(open_locks.is_empty(), "cancelled lock should not be open");
+    }
 }
@@ -0,0 +1,1284 @@
+use std::path::PathBuf;
+
+use casper_engine_test_support::{
+    DeployItemBuilder, ExecuteRequestBuilder, LmdbWasmTestBuilder, TransferRequestBuilder,
+    ARG_AMOUNT, DEFAULT_ACCOUNT_ADDR, DEFAULT_PAYMENT, LOCAL_GENESIS_REQUEST,
+};
+use casper_types::{
+    account::AccountHash, bytesrepr::FromBytes, contracts::ContractHash, runtime_args,
+    AddressableEntityHash, BlockTime, Key, RuntimeArgs, U256, U512,
+};
+
+use crate::tests::{assert_user_error, last_return, main_purse};
+
+// Contract constants
+const ORDERBOOK_WASM: &str = "orderbook.wasm";
+const MOCK_TOKEN_WASM: &str = "mock-token.wasm";
+const AMM_WASM: &str = "bonding_curve_amm.wasm";
+const ORDERBOOK_HASH_KEY: &str = "orderbook_hash";
+const ORDERBOOK_PACKAGE_KEY: &str = "orderbook_package";
+const MOCK_TOKEN_HASH_KEY: &str = "mock_token_hash";
+const AMM_HASH_KEY: &str = "bonding_curve_amm_hash";
+
+// The first market created gets ID 1
+const ESCROW_PURSE_KEY: &str = "1_cspr_escrow";
+
+// Order types and statuses as stored by the order book
+const LIMIT: u8 = 0;
+const IMMEDIATE_OR_CANCEL: u8 = 1;
+const FILL_OR_KILL: u8 = 2;
+const POST_ONLY: u8 = 3;
+const STATUS_OPEN: u8 = 0;
+const STATUS_FILLED: u8 = 1;
+const STATUS_CANCELLED: u8 = 2;
+const STATUS_PARTIAL: u8 = 3;
+const STATUS_EXPIRED: u8 = 4;
+
+// Error codes from contract
+const ERROR_NOT_AUTHORIZED: u16 = 1;
+const ERROR_INSUFFICIENT_FUNDS: u16 = 3;
+const ERROR_ORDER_NOT_FILLED: u16 = 10;
+const ERROR_WOULD_TAKE_LIQUIDITY: u16 = 11;
+const ERROR_CONDITIONAL_NOT_PENDING: u16 = 12;
+const ERROR_INVALID_FEE: u16 = 14;
+
+// Other traders, funded from the default account
+const SELLER: AccountHash = AccountHash::new([42u8; 32]);
+const TRADER: AccountHash = AccountHash::new([43u8; 32]);
+const ACCOUNT_FUNDING: u64 = 100_000_000_000_000; // 100,000 CSPR
+
+struct Setup {
+    builder: LmdbWasmTestBuilder,
+    orderbook: ContractHash,
+    // The key the token sees the order book calling from, which holds its tokens
+    orderbook_package: Key,
+    market: ContractHash,
+}
+
+fn install(builder: &mut LmdbWasmTestBuilder, wasm: &str, args: RuntimeArgs) {
+    let deploy_item = DeployItemBuilder::new()
+        .with_standard_payment(runtime_args! {
+            ARG_AMOUNT => *DEFAULT_PAYMENT
+        })
+        .with_session_code(PathBuf::from(wasm), args)
+        .with_authorization_keys(&[*DEFAULT_ACCOUNT_ADDR])
+        .with_address(*DEFAULT_ACCOUNT_ADDR)
+        .build();
+
+    let execute_request = ExecuteRequestBuilder::from_deploy_item(&deploy_item).build();
+    builder.exec(execute_request).commit().expect_success();
+}
+
+fn account_named_key(builder: &LmdbWasmTestBuilder, name: &str) -> Key {
+    let account = builder
+        .query(None, Key::Account(*DEFAULT_ACCOUNT_ADDR), &[])
+        .expect("should have default account");
+    *account
+        .as_account()
+        .expect("should be an account")
+        .named_keys()
+        .get(name)
+        .expect("should have named key")
+}
+
+fn contract_hash(builder: &LmdbWasmTestBuilder, name: &str) -> ContractHash {
+    ContractHash::new(
+        account_named_key(builder, name)
+            .into_hash_addr()
+            .expect("should be a contract hash"),
+    )
+}
+
+/// Deploy the order book and a mock token, list the token with 0 decimals so
+/// prices are motes per base unit, and fund the other traders
+fn setup() -> Setup {
+    let mut builder = LmdbWasmTestBuilder::default();
+    builder.run_genesis(LOCAL_GENESIS_REQUEST.clone()).commit();
+
+    install(
+        &mut builder,
+        ORDERBOOK_WASM,
+        runtime_args! {
+            "admin" => *DEFAULT_ACCOUNT_ADDR,
+        },
+    );
+    install(
+        &mut builder,
+        MOCK_TOKEN_WASM,
+        runtime_args! {
+            "decimals" => 0u8,
+        },
+    );
+
+    let orderbook = contract_hash(&builder, ORDERBOOK_HASH_KEY);
+    let orderbook_package = Key::Hash(
+        account_named_key(&builder, ORDERBOOK_PACKAGE_KEY)
+            .into_hash_addr()
+            .expect("should be a package hash"),
+    );
+    let market = contract_hash(&builder, MOCK_TOKEN_HASH_KEY);
+
+    for account in [SELLER, TRADER] {
+        let transfer = TransferRequestBuilder::new(ACCOUNT_FUNDING, account).build();
+        builder.transfer_and_commit(transfer).expect_success();
+    }
+
+    let mut setup = Setup {
+        builder,
+        orderbook,
+        orderbook_package,
+        market,
+    };
+    call(
+        &mut setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "create_market",
+        runtime_args! {
+            "token" => market,
+            "decimals" => Some(0u8),
+        },
+    )
+    .expect_success();
+    setup
+}
+
+fn call<'a>(
+    setup: &'a mut Setup,
+    sender: AccountHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+) -> &'a mut LmdbWasmTestBuilder {
+    call_at(setup, sender, entry_point, args, 0)
+}
+
+fn call_at<'a>(
+    setup: &'a mut Setup,
+    sender: AccountHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+    block_time: u64,
+) -> &'a mut LmdbWasmTestBuilder {
+    let execute_request = ExecuteRequestBuilder::contract_call_by_hash(
+        sender,
+        AddressableEntityHash::new(setup.orderbook.value()),
+        entry_point,
+        args,
+    )
+    .with_block_time(BlockTime::new(block_time))
+    .build();
+    setup.builder.exec(execute_request).commit()
+}
+
+fn place_buy_at(
+    setup: &mut Setup,
+    buyer: AccountHash,
+    price: u64,
+    amount: u64,
+    order_type: u8,
+    expires_at: u64,
+    block_time: u64,
+) -> &mut LmdbWasmTestBuilder {
+    let args = runtime_args! {
+        "market" => setup.market,
+        "price" => U512::from(price),
+        "amount" => U512::from(amount),
+        "payment_purse" => main_purse(&setup.builder, buyer),
+        "order_type" => order_type,
+        "expires_at" => expires_at,
+        "route_to_amm" => false,
+    };
+    call_at(setup, buyer, "place_buy_order", args, block_time)
+}
+
+fn place_buy(
+    setup: &mut Setup,
+    buyer: AccountHash,
+    price: u64,
+    amount: u64,
+    order_type: u8,
+) -> &mut LmdbWasmTestBuilder {
+    place_buy_at(setup, buyer, price, amount, order_type, 0, 0)
+}
+
+fn call_contract<'a>(
+    setup: &'a mut Setup,
+    contract: ContractHash,
+    sender: AccountHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+) -> &'a mut LmdbWasmTestBuilder {
+    let execute_request = ExecuteRequestBuilder::contract_call_by_hash(
+        sender,
+        AddressableEntityHash::new(contract.value()),
+        entry_point,
+        args,
+    )
+    .build();
+    setup.builder.exec(execute_request).commit()
+}
+
+fn token_call<'a>(
+    setup: &'a mut Setup,
+    sender: AccountHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+) -> &'a mut LmdbWasmTestBuilder {
+    let market = setup.market;
+    call_contract(setup, market, sender, entry_point, args)
+}
+
+fn token_balance(setup: &mut Setup, holder: Key) -> U256 {
+    token_call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "balance_of",
+        runtime_args! {
+            "address" => holder,
+        },
+    )
+    .expect_success();
+    last_return(&setup.builder)
+}
+
+/// Mint `amount` tokens to the seller and approve the order book to pull them
+fn fund_tokens(setup: &mut Setup, seller: AccountHash, amount: u64) {
+    token_call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "mint",
+        runtime_args! {
+            "owner" => Key::Account(seller),
+            "amount" => U256::from(amount),
+        },
+    )
+    .expect_success();
+    let spender = setup.orderbook_package;
+    token_call(
+        setup,
+        seller,
+        "approve",
+        runtime_args! {
+            "spender" => spender,
+            "amount" => U256::from(amount),
+        },
+    )
+    .expect_success();
+}
+
+fn deposit_tokens_call(
+    setup: &mut Setup,
+    seller: AccountHash,
+    amount: u64,
+    block_time: u64,
+) -> &mut LmdbWasmTestBuilder {
+    let market = setup.market;
+    call_at(
+        setup,
+        seller,
+        "deposit_tokens",
+        runtime_args! {
+            "market" => market,
+            "amount" => U512::from(amount),
+        },
+        block_time,
+    )
+}
+
+/// Fund the seller with `amount` tokens and deposit them into its order book balance
+fn deposit_tokens_at(setup: &mut Setup, seller: AccountHash, amount: u64, block_time: u64) {
+    fund_tokens(setup, seller, amount);
+    deposit_tokens_call(setup, seller, amount, block_time).expect_success();
+}
+
+/// Deposit tokens and place a sell limit order, returns the order ID
+fn place_sell_at(
+    setup: &mut Setup,
+    seller: AccountHash,
+    price: u64,
+    amount: u64,
+    block_time: u64,
+) -> u64 {
+    deposit_tokens_at(setup, seller, amount, block_time);
+    let market = setup.market;
+    call_at(
+        setup,
+        seller,
+        "place_sell_order",
+        runtime_args! {
+            "market" => market,
+            "price" => U512::from(price),
+            "amount" => U512::from(amount),
+            "order_type" => LIMIT,
+            "expires_at" => 0u64,
+        },
+        block_time,
+    )
+    .expect_success();
+    last_return(&setup.builder)
+}
+
+fn place_sell(setup: &mut Setup, seller: AccountHash, price: u64, amount: u64) -> u64 {
+    place_sell_at(setup, seller, price, amount, 0)
+}
+
+fn cancel_order(setup: &mut Setup, owner: AccountHash, order_id: u64) {
+    call(
+        setup,
+        owner,
+        "cancel_order",
+        runtime_args! {
+            "order_id" => order_id,
+        },
+    )
+    .expect_success();
+}
+
+fn escrow_balance(setup: &Setup) -> U512 {
+    let contract = setup
+        .builder
+        .query(None, Key::Hash(setup.orderbook.value()), &[])
+        .expect("should have order book contract");
+    let purse = contract
+        .as_contract()
+        .expect("should be a contract")
+        .named_keys()
+        .get(ESCROW_PURSE_KEY)
+        .and_then(Key::as_uref)
+        .copied()
+        .expect("should have escrow purse");
+    setup.builder.get_purse_balance(purse)
+}
+
+/// (filled, status) of an order, read from the start of its serialized form
+fn order_fill(setup: &mut Setup, order_id: u64) -> (U512, u8) {
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "get_order",
+        runtime_args! {
+            "order_id" => order_id,
+        },
+    )
+    .expect_success();
+    let order = setup
+        .builder
+        .get_last_exec_result()
+        .expect("must have execution result")
+        .ret()
+        .expect("must return a value")
+        .inner_bytes()
+        .clone();
+
+    // market and owner hashes, then side
+    let remainder = &order[32 + 32 + 1..];
+    let (_price, remainder) = U512::from_bytes(remainder).unwrap();
+    let (_amount, remainder) = U512::from_bytes(remainder).unwrap();
+    let (filled, remainder) = U512::from_bytes(remainder).unwrap();
+    let (_routed, remainder) = U512::from_bytes(remainder).unwrap();
+    let (status, _) = u8::from_bytes(remainder).unwrap();
+    (filled, status)
+}
+
+fn best_price(setup: &mut Setup, entry_point: &str) -> U512 {
+    let market = setup.market;
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        entry_point,
+        runtime_args! {
+            "market" => market,
+        },
+    )
+    .expect_success();
+    last_return(&setup.builder)
+}
+
+#[test]
+fn should_cross_at_maker_price_and_refund_improvement() {
+    let mut setup = setup();
+    place_sell(&mut setup, SELLER, 1_000, 100);
+
+    // Bid above the ask: fills at the ask and gets the difference back
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_200, 100, LIMIT).expect_success();
+    let order_id: u64 = last_return(&setup.builder);
+
+    assert_eq!(order_fill(&mut setup, order_id), (U512::from(100), STATUS_FILLED));
+    // Only the seller's proceeds at the maker price stay in escrow
+    assert_eq!(escrow_balance(&setup), U512::from(100_000));
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::MAX);
+}
+
+#[test]
+fn should_refund_rest_of_partially_filled_order_on_cancel() {
+    let mut setup = setup();
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 100, LIMIT).expect_success();
+    let order_id: u64 = last_return(&setup.builder);
+    // 100,000 cost plus the 1% fee allowance
+    assert_eq!(escrow_balance(&setup), U512::from(101_000));
+
+    let sell_id = place_sell(&mut setup, SELLER, 1_000, 40);
+    assert_eq!(order_fill(&mut setup, sell_id), (U512::from(40), STATUS_FILLED));
+    assert_eq!(order_fill(&mut setup, order_id), (U512::from(40), STATUS_PARTIAL));
+
+    cancel_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, order_id);
+
+    assert_eq!(order_fill(&mut setup, order_id), (U512::from(40), STATUS_CANCELLED));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::zero());
+    // The unfilled 60 are refunded; left are the seller's 40,000 and the
+    // buyer's unused 400 fee allowance on the filled part
+    assert_eq!(escrow_balance(&setup), U512::from(40_400));
+}
+
+#[test]
+fn should_recompute_best_prices_when_level_empties() {
+    let mut setup = setup();
+    place_sell(&mut setup, SELLER, 1_000, 10);
+    place_sell(&mut setup, SELLER, 1_100, 10);
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::from(1_000));
+
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 10, LIMIT).expect_success();
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::from(1_100));
+
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 900, 10, LIMIT).expect_success();
+    let best_bid_id: u64 = last_return(&setup.builder);
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 800, 10, LIMIT).expect_success();
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(900));
+
+    cancel_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, best_bid_id);
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(800));
+}
+
+fn user_orders(setup: &mut Setup, status: Option<u8>, offset: u64, limit: u64) -> Vec<u64> {
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "get_user_orders",
+        runtime_args! {
+            "account" => *DEFAULT_ACCOUNT_ADDR,
+            "status" => status,
+            "offset" => offset,
+            "limit" => limit,
+        },
+    )
+    .expect_success();
+    last_return(&setup.builder)
+}
+
+#[test]
+fn should_filter_user_orders_within_history_window() {
+    let mut setup = setup();
+    for price in [1_000, 900, 800] {
+        place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, price, 10, LIMIT).expect_success();
+    }
+    cancel_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1);
+
+    assert_eq!(user_orders(&mut setup, None, 1, 5), vec![2, 3]);
+    // The window covers history entries, the status filter applies inside it
+    assert_eq!(user_orders(&mut setup, Some(STATUS_CANCELLED), 0, 2), vec![1]);
+    assert!(user_orders(&mut setup, Some(STATUS_CANCELLED), 1, 2).is_empty());
+    // Open orders are paged over the open index
+    assert_eq!(user_orders(&mut setup, Some(STATUS_OPEN), 1, 1), vec![3]);
+}
+
+#[test]
+fn should_cancel_rest_of_immediate_or_cancel_order() {
+    let mut setup = setup();
+    place_sell(&mut setup, SELLER, 1_000, 10);
+
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 30, IMMEDIATE_OR_CANCEL).expect_success();
+    let order_id: u64 = last_return(&setup.builder);
+
+    assert_eq!(order_fill(&mut setup, order_id), (U512::from(10), STATUS_CANCELLED));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::zero());
+    // Only the seller's proceeds stay in escrow, the unfilled 20 were refunded
+    assert_eq!(escrow_balance(&setup), U512::from(10_000));
+}
+
+#[test]
+fn should_revert_fill_or_kill_that_cannot_fill() {
+    let mut setup = setup();
+    place_sell(&mut setup, SELLER, 1_000, 10);
+
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 20, FILL_OR_KILL).expect_failure();
+    assert_user_error(&setup.builder, ERROR_ORDER_NOT_FILLED);
+
+    // The partial match was undone
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::from(1_000));
+    assert_eq!(escrow_balance(&setup), U512::zero());
+}
+
+#[test]
+fn should_revert_post_only_that_would_take() {
+    let mut setup = setup();
+    place_sell(&mut setup, SELLER, 1_000, 10);
+
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 10, POST_ONLY).expect_failure();
+    assert_user_error(&setup.builder, ERROR_WOULD_TAKE_LIQUIDITY);
+
+    // Resting below the ask is fine
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 900, 10, POST_ONLY).expect_success();
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(900));
+}
+
+fn set_lot_size(setup: &mut Setup, lot_size: u64) {
+    let market = setup.market;
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "set_market_params",
+        runtime_args! {
+            "market" => market,
+            "tick_size" => U512::one(),
+            "lot_size" => U512::from(lot_size),
+            "min_notional" => U512::zero(),
+        },
+    )
+    .expect_success();
+}
+
+#[test]
+fn should_stop_market_buy_at_budget_in_whole_lots() {
+    let mut setup = setup();
+    set_lot_size(&mut setup, 10);
+    place_sell(&mut setup, SELLER, 1_000, 10);
+    place_sell(&mut setup, SELLER, 1_100, 20);
+
+    let buyer = *DEFAULT_ACCOUNT_ADDR;
+    let market = setup.market;
+    let payment_purse = main_purse(&setup.builder, buyer);
+    call(
+        &mut setup,
+        buyer,
+        "place_market_buy",
+        runtime_args! {
+            "market" => market,
+            "amount" => U512::from(30),
+            "worst_price" => U512::from(1_100),
+            "max_spend" => U512::from(25_000),
+            "payment_purse" => payment_purse,
+            "route_to_amm" => false,
+        },
+    )
+    .expect_success();
+
+    // The 15,000 left after the first level affords 13 tokens at 1,100, rounded down to a lot
+    let (filled, routed, average_price): (U512, U512, U512) = last_return(&setup.builder);
+    assert_eq!(filled, U512::from(20));
+    assert_eq!(routed, U512::zero());
+    assert_eq!(average_price, U512::from(1_050));
+
+    // The unused 4,000 of max_spend was refunded, the sellers' 21,000 stay in escrow
+    assert_eq!(escrow_balance(&setup), U512::from(21_000));
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::from(1_100));
+}
+
+#[test]
+fn should_fill_market_sell_down_to_worst_price() {
+    let mut setup = setup();
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 10, LIMIT).expect_success();
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 800, 10, LIMIT).expect_success();
+
+    deposit_tokens_at(&mut setup, SELLER, 30, 0);
+    let market = setup.market;
+    call(
+        &mut setup,
+        SELLER,
+        "place_market_sell",
+        runtime_args! {
+            "market" => market,
+            "amount" => U512::from(30),
+            "worst_price" => U512::from(900),
+        },
+    )
+    .expect_success();
+
+    let (filled, average_price): (U512, U512) = last_return(&setup.builder);
+    assert_eq!(filled, U512::from(10));
+    assert_eq!(average_price, U512::from(1_000));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(800));
+}
+
+const KEEPER_BOUNTY: u64 = 500;
+
+fn set_keeper_bounty(setup: &mut Setup, bounty: u64) {
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "set_keeper_bounty",
+        runtime_args! {
+            "bounty" => U512::from(bounty),
+        },
+    )
+    .expect_success();
+}
+
+/// Place a conditional buy for the default account that triggers once the
+/// last price drops to `trigger_price`, returns the conditional order ID
+fn place_conditional_buy(setup: &mut Setup, trigger_price: u64, price: u64, amount: u64) -> u64 {
+    let buyer = *DEFAULT_ACCOUNT_ADDR;
+    let market = setup.market;
+    let payment_purse = main_purse(&setup.builder, buyer);
+    call(
+        setup,
+        buyer,
+        "place_conditional_buy",
+        runtime_args! {
+            "market" => market,
+            "trigger_price" => U512::from(trigger_price),
+            "trigger_above" => false,
+            "price" => U512::from(price),
+            "amount" => U512::from(amount),
+            "payment_purse" => payment_purse,
+        },
+    )
+    .expect_success();
+    last_return(&setup.builder)
+}
+
+fn trigger_orders(setup: &mut Setup, keeper: AccountHash) -> u32 {
+    let market = setup.market;
+    call(
+        setup,
+        keeper,
+        "trigger_orders",
+        runtime_args! {
+            "market" => market,
+            "max_orders" => 10u32,
+        },
+    )
+    .expect_success();
+    last_return(&setup.builder)
+}
+
+fn cancel_conditional_order(
+    setup: &mut Setup,
+    owner: AccountHash,
+    conditional_id: u64,
+) -> &mut LmdbWasmTestBuilder {
+    call(
+        setup,
+        owner,
+        "cancel_conditional_order",
+        runtime_args! {
+            "conditional_id" => conditional_id,
+        },
+    )
+}
+
+fn withdraw_cspr(setup: &mut Setup, account: AccountHash, amount: u64) -> &mut LmdbWasmTestBuilder {
+    let market = setup.market;
+    call(
+        setup,
+        account,
+        "withdraw_cspr",
+        runtime_args! {
+            "market" => market,
+            "amount" => U512::from(amount),
+        },
+    )
+}
+
+/// Check an account's CSPR credit by withdrawing one mote more than expected, then all of it
+fn assert_cspr_credit(setup: &mut Setup, account: AccountHash, expected: u64) {
+    withdraw_cspr(setup, account, expected + 1).expect_failure();
+    assert_user_error(&setup.builder, ERROR_INSUFFICIENT_FUNDS);
+    withdraw_cspr(setup, account, expected).expect_success();
+}
+
+/// Trade `amount` tokens at `price` between the seller and the default account
+fn trade(setup: &mut Setup, price: u64, amount: u64) {
+    place_sell(setup, SELLER, price, amount);
+    place_buy(setup, *DEFAULT_ACCOUNT_ADDR, price, amount, LIMIT).expect_success();
+}
+
+#[test]
+fn should_trigger_conditional_order_once_last_price_crosses() {
+    let mut setup = setup();
+    set_keeper_bounty(&mut setup, KEEPER_BOUNTY);
+
+    // 9,000 cost, 90 fee allowance and the keeper bounty
+    let conditional_id = place_conditional_buy(&mut setup, 900, 900, 10);
+    assert_eq!(escrow_balance(&setup), U512::from(9_590));
+
+    trade(&mut setup, 1_000, 10);
+    assert_eq!(trigger_orders(&mut setup, SELLER), 0);
+
+    trade(&mut setup, 900, 10);
+    assert_eq!(escrow_balance(&setup), U512::from(28_590));
+    assert_eq!(trigger_orders(&mut setup, SELLER), 1);
+
+    // The bounty went to the keeper, the order now rests on the book
+    assert_eq!(escrow_balance(&setup), U512::from(28_090));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(900));
+
+    cancel_conditional_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, conditional_id).expect_failure();
+    assert_user_error(&setup.builder, ERROR_CONDITIONAL_NOT_PENDING);
+}
+
+#[test]
+fn should_credit_owner_refunds_when_keeper_triggers() {
+    let mut setup = setup();
+    set_keeper_bounty(&mut setup, KEEPER_BOUNTY);
+
+    // 10,000 cost, 100 fee allowance and the keeper bounty
+    place_conditional_buy(&mut setup, 900, 1_000, 10);
+    trade(&mut setup, 900, 10);
+    place_sell(&mut setup, SELLER, 800, 10);
+    assert_eq!(escrow_balance(&setup), U512::from(19_600));
+
+    // Fills at 800 when triggered; the owner is not the caller, so the
+    // 2,000 price improvement and 100 fee allowance are credited, not sent
+    assert_eq!(trigger_orders(&mut setup, SELLER), 1);
+    assert_eq!(escrow_balance(&setup), U512::from(19_100));
+
+    assert_cspr_credit(&mut setup, *DEFAULT_ACCOUNT_ADDR, 2_100);
+    // Only the seller's proceeds from both trades are left
+    assert_eq!(escrow_balance(&setup), U512::from(17_000));
+}
+
+#[test]
+fn should_refund_cancelled_conditional_order_to_owner_only() {
+    let mut setup = setup();
+    set_keeper_bounty(&mut setup, KEEPER_BOUNTY);
+    let conditional_id = place_conditional_buy(&mut setup, 900, 900, 10);
+
+    cancel_conditional_order(&mut setup, SELLER, conditional_id).expect_failure();
+    assert_user_error(&setup.builder, ERROR_NOT_AUTHORIZED);
+
+    cancel_conditional_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, conditional_id).expect_success();
+    // Escrow and bounty were paid back
+    assert_eq!(escrow_balance(&setup), U512::zero());
+    assert_eq!(trigger_orders(&mut setup, SELLER), 0);
+}
+
+#[test]
+fn should_skip_expired_orders_and_purge_them() {
+    let mut setup = setup();
+    let buyer = *DEFAULT_ACCOUNT_ADDR;
+    place_buy_at(&mut setup, buyer, 1_000, 10, LIMIT, 10_000, 1_000).expect_success();
+    let expiring_id: u64 = last_return(&setup.builder);
+    place_buy_at(&mut setup, buyer, 900, 10, LIMIT, 0, 1_000).expect_success();
+    let resting_id: u64 = last_return(&setup.builder);
+
+    // The expired bid is skipped and refunded, the sell fills against the next one
+    place_sell_at(&mut setup, SELLER, 900, 10, 20_000);
+    assert_eq!(order_fill(&mut setup, expiring_id), (U512::zero(), STATUS_EXPIRED));
+    assert_eq!(order_fill(&mut setup, resting_id), (U512::from(10), STATUS_FILLED));
+    // The 10,100 expired escrow and the filled bid's 90 fee allowance
+    assert_cspr_credit(&mut setup, buyer, 10_190);
+
+    place_buy_at(&mut setup, buyer, 800, 10, LIMIT, 30_000, 20_000).expect_success();
+    let purged_id: u64 = last_return(&setup.builder);
+
+    let market = setup.market;
+    call_at(
+        &mut setup,
+        SELLER,
+        "purge_expired",
+        runtime_args! {
+            "market" => market,
+            "max_orders" => 10u32,
+        },
+        40_000,
+    )
+    .expect_success();
+    let purged: u32 = last_return(&setup.builder);
+
+    assert_eq!(purged, 1);
+    assert_eq!(order_fill(&mut setup, purged_id), (U512::zero(), STATUS_EXPIRED));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::zero());
+    assert_cspr_credit(&mut setup, buyer, 8_080);
+}
+
+#[test]
+fn should_hold_deposited_tokens_under_orderbook_package() {
+    let mut setup = setup();
+    let seller = Key::Account(SELLER);
+    let orderbook = setup.orderbook_package;
+
+    // Without an allowance the token refuses to move anything
+    deposit_tokens_call(&mut setup, SELLER, 100, 0).expect_failure();
+
+    deposit_tokens_at(&mut setup, SELLER, 100, 0);
+    assert_eq!(token_balance(&mut setup, seller), U256::zero());
+    assert_eq!(token_balance(&mut setup, orderbook), U256::from(100));
+
+    let market = setup.market;
+    let withdraw_args = |amount: u64| {
+        runtime_args! {
+            "market" => market,
+            "amount" => U512::from(amount),
+        }
+    };
+    call(&mut setup, SELLER, "withdraw_tokens", withdraw_args(101)).expect_failure();
+    assert_user_error(&setup.builder, ERROR_INSUFFICIENT_FUNDS);
+    call(&mut setup, SELLER, "withdraw_tokens", withdraw_args(40)).expect_success();
+
+    assert_eq!(token_balance(&mut setup, seller), U256::from(40));
+    assert_eq!(token_balance(&mut setup, orderbook), U256::from(60));
+}
+
+fn set_fee_schedule(
+    setup: &mut Setup,
+    maker_fee_bps: i32,
+    taker_fee_bps: u32,
+) -> &mut LmdbWasmTestBuilder {
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "set_fee_schedule",
+        runtime_args! {
+            "maker_fee_bps" => maker_fee_bps,
+            "taker_fee_bps" => taker_fee_bps,
+        },
+    )
+}
+
+fn withdraw_fees(setup: &mut Setup, amount: u64) -> &mut LmdbWasmTestBuilder {
+    let market = setup.market;
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "withdraw_fees",
+        runtime_args! {
+            "market" => market,
+            "recipient" => *DEFAULT_ACCOUNT_ADDR,
+            "amount" => U512::from(amount),
+        },
+    )
+}
+
+#[test]
+fn should_pay_maker_rebate_out_of_taker_fee() {
+    let mut setup = setup();
+    // A rebate larger than the taker fee would pay out more than is collected
+    set_fee_schedule(&mut setup, -40, 30).expect_failure();
+    assert_user_error(&setup.builder, ERROR_INVALID_FEE);
+    set_fee_schedule(&mut setup, -10, 30).expect_success();
+
+    place_sell(&mut setup, SELLER, 1_000, 100);
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 100, LIMIT).expect_success();
+
+    // The taker paid 100,000 plus 300 and got the rest of its fee allowance back
+    assert_eq!(escrow_balance(&setup), U512::from(100_300));
+    // The maker earns a 100 rebate on top of the trade value
+    assert_cspr_credit(&mut setup, SELLER, 100_100);
+
+    // 300 taker fee less the 100 rebate
+    withdraw_fees(&mut setup, 201).expect_failure();
+    assert_user_error(&setup.builder, ERROR_INSUFFICIENT_FUNDS);
+    withdraw_fees(&mut setup, 200).expect_success();
+    assert_eq!(escrow_balance(&setup), U512::zero());
+}
+
+// Self-trade prevention modes
+const CANCEL_NEWEST: u8 = 0;
+const CANCEL_OLDEST: u8 = 1;
+const CANCEL_BOTH: u8 = 2;
+
+fn withdraw_tokens(
+    setup: &mut Setup,
+    account: AccountHash,
+    amount: u64,
+) -> &mut LmdbWasmTestBuilder {
+    let market = setup.market;
+    call(
+        setup,
+        account,
+        "withdraw_tokens",
+        runtime_args! {
+            "market" => market,
+            "amount" => U512::from(amount),
+        },
+    )
+}
+
+/// The default account rests an ask at 1,000 below the seller's at 1,100, then
+/// sets `mode` and bids for 20 at 1,100. Returns (own ask ID, bid ID)
+fn cross_own_ask(setup: &mut Setup, mode: u8) -> (u64, u64) {
+    let trader = *DEFAULT_ACCOUNT_ADDR;
+    let own_ask = place_sell(setup, trader, 1_000, 10);
+    place_sell(setup, SELLER, 1_100, 10);
+
+    call(
+        setup,
+        trader,
+        "set_self_trade_mode",
+        runtime_args! {
+            "mode" => mode,
+        },
+    )
+    .expect_success();
+    place_buy(setup, trader, 1_100, 20, LIMIT).expect_success();
+    (own_ask, last_return(&setup.builder))
+}
+
+#[test]
+fn should_cancel_newest_order_on_self_trade_by_default() {
+    let mut setup = setup();
+    let (own_ask, bid) = cross_own_ask(&mut setup, CANCEL_NEWEST);
+
+    // The whole 22,220 bid escrow was refunded, the resting ask is untouched
+    assert_eq!(order_fill(&mut setup, bid), (U512::zero(), STATUS_CANCELLED));
+    assert_eq!(order_fill(&mut setup, own_ask), (U512::zero(), STATUS_OPEN));
+    assert_eq!(escrow_balance(&setup), U512::zero());
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::from(1_000));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::zero());
+    withdraw_tokens(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1).expect_failure();
+    assert_user_error(&setup.builder, ERROR_INSUFFICIENT_FUNDS);
+}
+
+#[test]
+fn should_cancel_oldest_order_on_self_trade_and_keep_matching() {
+    let mut setup = setup();
+    let (own_ask, bid) = cross_own_ask(&mut setup, CANCEL_OLDEST);
+
+    assert_eq!(order_fill(&mut setup, own_ask), (U512::zero(), STATUS_CANCELLED));
+    assert_eq!(order_fill(&mut setup, bid), (U512::from(10), STATUS_PARTIAL));
+    // The seller's 11,000 and the 11,110 escrow of the 10 still resting
+    assert_eq!(escrow_balance(&setup), U512::from(22_110));
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::MAX);
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(1_100));
+    // The cancelled ask's tokens are back, alongside the 10 bought
+    withdraw_tokens(&mut setup, *DEFAULT_ACCOUNT_ADDR, 20).expect_success();
+}
+
+#[test]
+fn should_cancel_both_orders_on_self_trade() {
+    let mut setup = setup();
+    let (own_ask, bid) = cross_own_ask(&mut setup, CANCEL_BOTH);
+
+    assert_eq!(order_fill(&mut setup, own_ask), (U512::zero(), STATUS_CANCELLED));
+    assert_eq!(order_fill(&mut setup, bid), (U512::zero(), STATUS_CANCELLED));
+    assert_eq!(escrow_balance(&setup), U512::zero());
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::from(1_100));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::zero());
+    withdraw_tokens(&mut setup, *DEFAULT_ACCOUNT_ADDR, 10).expect_success();
+}
+
+fn amend_order(
+    setup: &mut Setup,
+    owner: AccountHash,
+    order_id: u64,
+    price: u64,
+    amount: u64,
+) -> &mut LmdbWasmTestBuilder {
+    let payment_purse = main_purse(&setup.builder, owner);
+    call(
+        setup,
+        owner,
+        "amend_order",
+        runtime_args! {
+            "order_id" => order_id,
+            "price" => U512::from(price),
+            "amount" => U512::from(amount),
+            "payment_purse" => payment_purse,
+        },
+    )
+}
+
+#[test]
+fn should_keep_queue_place_and_refund_when_buy_is_reduced() {
+    let mut setup = setup();
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 20, LIMIT).expect_success();
+    let first: u64 = last_return(&setup.builder);
+    place_buy(&mut setup, SELLER, 1_000, 10, LIMIT).expect_success();
+    let second: u64 = last_return(&setup.builder);
+
+    amend_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, first, 1_000, 15).expect_success();
+    // 5,050 of the first bid's 20,200 escrow was refunded
+    assert_eq!(escrow_balance(&setup), U512::from(25_250));
+
+    place_sell(&mut setup, TRADER, 1_000, 15);
+    assert_eq!(order_fill(&mut setup, first), (U512::from(15), STATUS_FILLED));
+    assert_eq!(order_fill(&mut setup, second), (U512::zero(), STATUS_OPEN));
+}
+
+#[test]
+fn should_requeue_buy_amended_to_new_price() {
+    let mut setup = setup();
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 10, LIMIT).expect_success();
+    let first: u64 = last_return(&setup.builder);
+    place_buy(&mut setup, SELLER, 900, 10, LIMIT).expect_success();
+    let second: u64 = last_return(&setup.builder);
+
+    // Joins the 900 level behind the order already there
+    amend_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, first, 900, 10).expect_success();
+    assert_eq!(escrow_balance(&setup), U512::from(18_180));
+
+    place_sell(&mut setup, TRADER, 900, 10);
+    assert_eq!(order_fill(&mut setup, second), (U512::from(10), STATUS_FILLED));
+    assert_eq!(order_fill(&mut setup, first), (U512::zero(), STATUS_OPEN));
+}
+
+#[test]
+fn should_top_up_and_cross_when_buy_is_amended_above_ask() {
+    let mut setup = setup();
+    place_sell(&mut setup, TRADER, 1_000, 10);
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 900, 10, LIMIT).expect_success();
+    let order_id: u64 = last_return(&setup.builder);
+    assert_eq!(escrow_balance(&setup), U512::from(9_090));
+
+    // Takes the extra 11,110 escrow from the payment purse, then fills 10 at the ask
+    // and gets back the 100 fee allowance it did not use
+    amend_order(&mut setup, *DEFAULT_ACCOUNT_ADDR, order_id, 1_000, 20).expect_success();
+
+    assert_eq!(order_fill(&mut setup, order_id), (U512::from(10), STATUS_PARTIAL));
+    // The seller's 10,000 and the 10,100 escrow of the 10 still resting
+    assert_eq!(escrow_balance(&setup), U512::from(20_100));
+    assert_eq!(best_price(&mut setup, "get_best_ask"), U512::MAX);
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(1_000));
+}
+
+const BUY: u8 = 0;
+const SELL: u8 = 1;
+
+fn batch_orders(
+    setup: &mut Setup,
+    cancels: Vec<u64>,
+    orders: Vec<(u8, U512, U512)>,
+) -> &mut LmdbWasmTestBuilder {
+    let trader = *DEFAULT_ACCOUNT_ADDR;
+    let market = setup.market;
+    let payment_purse = main_purse(&setup.builder, trader);
+    call(
+        setup,
+        trader,
+        "batch_orders",
+        runtime_args! {
+            "market" => market,
+            "cancels" => cancels,
+            "orders" => orders,
+            "order_type" => LIMIT,
+            "expires_at" => 0u64,
+            "payment_purse" => payment_purse,
+        },
+    )
+}
+
+#[test]
+fn should_revert_batch_cancels_when_an_order_fails() {
+    let mut setup = setup();
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 900, 10, LIMIT).expect_success();
+    let resting: u64 = last_return(&setup.builder);
+
+    // No tokens were deposited, so the sell fails and the cancel is undone
+    let orders = vec![
+        (BUY, U512::from(950), U512::from(10)),
+        (SELL, U512::from(1_000), U512::from(10)),
+    ];
+    batch_orders(&mut setup, vec![resting], orders).expect_failure();
+    assert_user_error(&setup.builder, ERROR_INSUFFICIENT_FUNDS);
+    assert_eq!(order_fill(&mut setup, resting), (U512::zero(), STATUS_OPEN));
+    assert_eq!(escrow_balance(&setup), U512::from(9_090));
+
+    let orders = vec![
+        (BUY, U512::from(950), U512::from(10)),
+        (BUY, U512::from(940), U512::from(10)),
+    ];
+    batch_orders(&mut setup, vec![resting], orders).expect_success();
+    let placed: Vec<u64> = last_return(&setup.builder);
+
+    assert_eq!(placed, vec![resting + 1, resting + 2]);
+    assert_eq!(order_fill(&mut setup, resting), (U512::zero(), STATUS_CANCELLED));
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(950));
+    assert_eq!(escrow_balance(&setup), U512::from(9_595 + 9_494));
+}
+
+#[test]
+fn should_cancel_all_orders_in_one_market_only() {
+    let mut setup = setup();
+    let first_market = setup.market;
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 900, 10, LIMIT).expect_success();
+    let first_order: u64 = last_return(&setup.builder);
+
+    // List a second token and trade in it from here on
+    install(
+        &mut setup.builder,
+        MOCK_TOKEN_WASM,
+        runtime_args! {
+            "decimals" => 0u8,
+        },
+    );
+    let second_market = contract_hash(&setup.builder, MOCK_TOKEN_HASH_KEY);
+    call(
+        &mut setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "create_market",
+        runtime_args! {
+            "token" => second_market,
+            "decimals" => Some(0u8),
+        },
+    )
+    .expect_success();
+    setup.market = second_market;
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 900, 10, LIMIT).expect_success();
+    let second_order: u64 = last_return(&setup.builder);
+
+    call(
+        &mut setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "cancel_all",
+        runtime_args! {
+            "market" => first_market,
+        },
+    )
+    .expect_success();
+    let cancelled: u32 = last_return(&setup.builder);
+
+    assert_eq!(cancelled, 1);
+    assert_eq!(order_fill(&mut setup, first_order), (U512::zero(), STATUS_CANCELLED));
+    assert_eq!(order_fill(&mut setup, second_order), (U512::zero(), STATUS_OPEN));
+    // The first market's escrow was refunded
+    assert_eq!(escrow_balance(&setup), U512::zero());
+    assert_eq!(best_price(&mut setup, "get_best_bid"), U512::from(900));
+}
+
+#[test]
+fn should_credit_sale_proceeds_until_withdrawn() {
+    let mut setup = setup();
+    place_buy(&mut setup, *DEFAULT_ACCOUNT_ADDR, 1_000, 10, LIMIT).expect_success();
+
+    // Even as the caller, the seller is credited rather than paid during matching
+    place_sell(&mut setup, SELLER, 1_000, 10);
+    assert_eq!(escrow_balance(&setup), U512::from(10_100));
+
+    withdraw_cspr(&mut setup, SELLER, 4_000).expect_success();
+    assert_eq!(escrow_balance(&setup), U512::from(6_100));
+    assert_cspr_credit(&mut setup, SELLER, 6_000);
+
+    // The resting buy's unused fee allowance is the buyer's to withdraw
+    assert_cspr_credit(&mut setup, *DEFAULT_ACCOUNT_ADDR, 100);
+    assert_eq!(escrow_balance(&setup), U512::zero());
+}
+
+/// Install a flat bonding curve selling at `price` per token and link it to the market
+fn link_amm(setup: &mut Setup, price: u64) -> ContractHash {
+    install(
+        &mut setup.builder,
+        AMM_WASM,
+        runtime_args! {
+            "admin" => *DEFAULT_ACCOUNT_ADDR,
+        },
+    );
+    let amm = contract_hash(&setup.builder, AMM_HASH_KEY);
+    call_contract(
+        setup,
+        amm,
+        *DEFAULT_ACCOUNT_ADDR,
+        "initialize",
+        runtime_args! {
+            "initial_price" => U512::from(price),
+            "reserve_ratio" => U512::zero(),
+        },
+    )
+    .expect_success();
+
+    let market = setup.market;
+    call(
+        setup,
+        *DEFAULT_ACCOUNT_ADDR,
+        "set_market_amm",
+        runtime_args! {
+            "market" => market,
+            "amm" => Some(amm),
+        },
+    )
+    .expect_success();
+    amm
+}
+
+/// Tokens the default account holds on the curve
+fn curve_balance(setup: &mut Setup, amm: ContractHash) -> U512 {
+    call_contract(
+        setup,
+        amm,
+        *DEFAULT_ACCOUNT_ADDR,
+        "get_balance",
+        runtime_args! {
+            "account" => *DEFAULT_ACCOUNT_ADDR,
+        },
+    )
+    .expect_success();
+    last_return(&setup.builder)
+}
+
+fn place_routed_buy(setup: &mut Setup, price: u64, amount: u64) -> u64 {
+    let buyer = *DEFAULT_ACCOUNT_ADDR;
+    let args = runtime_args! {
+        "market" => setup.market,
+        "price" => U512::from(price),
+        "amount" => U512::from(amount),
+        "payment_purse" => main_purse(&setup.builder, buyer),
+        "order_type" => IMMEDIATE_OR_CANCEL,
+        "expires_at" => 0u64,
+        "route_to_amm" => true,
+    };
+    call(setup, buyer, "place_buy_order", args).expect_success();
+    last_return(&setup.builder)
+}
+
+#[test]
+fn should_route_rest_of_ioc_buy_to_curve() {
+    let mut setup = setup();
+    let amm = link_amm(&mut setup, 900);
+    place_sell(&mut setup, SELLER, 1_000, 10);
+
+    let order_id = place_routed_buy(&mut setup, 1_000, 20);
+
+    // 10 from the book at 1,000, the other 10 from the curve for 9,000
+    assert_eq!(order_fill(&mut setup, order_id), (U512::from(10), STATUS_FILLED));
+    assert_eq!(curve_balance(&mut setup, amm), U512::from(10));
+    // The 1,200 left of the 20,200 escrow went back to the buyer
+    assert_eq!(escrow_balance(&setup), U512::from(10_000));
+}
+
+#[test]
+fn should_keep_book_fills_when_fees_leave_no_room_for_curve() {
+    let mut setup = setup();
+    set_fee_schedule(&mut setup, 0, 100).expect_success();
+    let amm = link_amm(&mut setup, 1_000);
+    place_sell(&mut setup, SELLER, 1_000, 10);
+
+    // The book fill costs 10,100 with the taker fee, so the curve's 10,000 for the
+    // rest would take the route above 1,000 per token
+    let order_id = place_routed_buy(&mut setup, 1_000, 20);
+
+    assert_eq!(order_fill(&mut setup, order_id), (U512::from(10), STATUS_CANCELLED));
+    assert_eq!(curve_balance(&mut setup, amm), U512::zero());
+    // The seller's 10,000 and the 100 fee, the remainder's escrow was refunded
+    assert_eq!(escrow_balance(&setup), U512::from(10_100));
+}
+
+#[test]
+fn should_keep_book_fills_when_market_buy_budget_runs_out() {
+    let mut setup = setup();
+    let amm = link_amm(&mut setup, 900);
+    place_sell(&mut setup, SELLER, 1_000, 10);
+
+    let buyer = *DEFAULT_ACCOUNT_ADDR;
+    let market = setup.market;
+    let payment_purse = main_purse(&setup.builder, buyer);
+    call(
+        &mut setup,
+        buyer,
+        "place_market_buy",
+        runtime_args! {
+            "market" => market,
+            "amount" => U512::from(20),
+            "worst_price" => U512::from(1_000),
+            "max_spend" => U512::from(10_000),
+            "payment_purse" => payment_purse,
+            "route_to_amm" => true,
+        },
+    )
+    .expect_success();
+
+    // The book used the whole budget, so the curve is skipped
+    let (filled, routed, average_price): (U512, U512, U512) = last_return(&setup.builder);
+    assert_eq!(filled, U512::from(10));
+    assert_eq!(routed, U512::zero());
+    assert_eq!(average_price, U512::from(1_000));
+    assert_eq!(curve_balance(&mut setup, amm), U512::zero());
+    assert_eq!(escrow_balance(&setup), U512::from(10_000));
+}
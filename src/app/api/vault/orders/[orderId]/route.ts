@@ -1,5 +1,5 @@
 import { NextRequest, NextResponse } from "next/server";
-import { getLockedAmount, orderExists } from "@/lib/vault";
+import { getLockRecord, motesToCSPR } from "@/lib/vault";
 import { enforceRateLimit, RateLimitError } from "@/lib/rate-limit";
 import { appConfig } from "@/lib/config";
 
@@ -7,10 +7,11 @@ type RouteContext = {
   params: Promise<{ orderId: string }>;
 };
 
-// GET /api/vault/orders/[orderId]
-// Returns the locked CSPR amount for a specific order
+// GET /api/vault/orders/[orderId]?owner=<account hash>
+// Returns the lock of an owner's order, order IDs are only unique per owner
 export async function GET(request: NextRequest, context: RouteContext) {
   const { orderId } = await context.params;
+  const owner = request.nextUrl.searchParams.get("owner");
 
   // Rate limiting
   try {
@@ -31,24 +32,30 @@ export async function GET(request: NextRequest, context: RouteContext) {
       );
     }
 
-    // Check if order exists
-    const exists = await orderExists(orderId);
-    if (!exists) {
+    if (!owner) {
       return NextResponse.json(
-        { error: "Order not found in vault", orderId },
-        { status: 404 }
+        { error: "Missing owner account hash", orderId },
+        { status: 400 }
       );
     }
 
-    // Get locked amount
-    const locked = await getLockedAmount(orderId);
+    const lock = await getLockRecord(owner, orderId);
+    if (!lock) {
+      return NextResponse.json(
+        { error: "Order not found in vault", orderId, owner },
+        { status: 404 }
+      );
+    }
 
     return NextResponse.json({
       orderId,
+      owner,
+      status: lock.status,
       locked: {
-        motes: locked.amountMotes,
-        cspr: locked.amountCSPR,
+        motes: lock.remainingMotes,
+        cspr: motesToCSPR(lock.remainingMotes),
       },
+      expiresAt: lock.expiresAt,
       vaultAccountHash: appConfig.vault.accountHash,
     });
   } catch (error) {
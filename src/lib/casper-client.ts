@@ -117,6 +117,7 @@ export async function buildLockCsprDeploy(params: {
   vaultContractHash: string; // The contract hash of the token vault (without "hash-" prefix)
   orderId: string;
   amountMotes: string; // Amount in motes to lock
+  expiresAt?: number; // Block time (ms) after which the lock can be expired, 0 = never
   chainName?: string;
   paymentAmount?: string;
 }) {
@@ -125,6 +126,7 @@ export async function buildLockCsprDeploy(params: {
     vaultContractHash,
     orderId,
     amountMotes,
+    expiresAt = 0,
     chainName = publicRuntime.chainName,
     paymentAmount = "3000000000", // 3 CSPR for gas (session code + transfer)
   } = params;
@@ -143,6 +145,7 @@ export async function buildLockCsprDeploy(params: {
     vault_contract_hash: new CLByteArray(contractHashBytes),
     order_id: CLValueBuilder.string(orderId),
     amount: CLValueBuilder.u512(amountMotes),
+    expires_at: CLValueBuilder.u64(expiresAt),
   });
 
   const deploy = DeployUtil.makeDeploy(
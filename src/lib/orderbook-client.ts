@@ -6,6 +6,7 @@
 "use client";
 
 import {
+  CLByteArray,
   CLPublicKey,
   CLValueBuilder,
   DeployUtil,
@@ -49,6 +50,16 @@ export interface OrderBook {
   };
 }
 
+// Time-in-force of a limit order, matches the contract's OrderType codes
+export type OrderType = "limit" | "ioc" | "fok" | "post_only";
+
+const ORDER_TYPE_CODES: Record<OrderType, number> = {
+  limit: 0,
+  ioc: 1,
+  fok: 2,
+  post_only: 3,
+};
+
 export interface PlaceOrderResult {
   success: boolean;
   deployHash: string;
@@ -80,6 +91,10 @@ const getChainName = () => {
   return "casper-test";
 };
 
+// Markets are addressed by their token's contract hash
+const marketArg = (tokenContractHash: string) =>
+  new CLByteArray(Uint8Array.from(Buffer.from(tokenContractHash.replace("hash-", ""), "hex")));
+
 // ============================================================================
 // API Functions
 // ============================================================================
@@ -136,11 +151,23 @@ export async function getOrder(orderId: string): Promise<Order> {
  * Place a buy limit order
  */
 export async function placeBuyOrder(params: {
+  tokenContractHash: string; // Market to trade in (token contract hash, "hash-" optional)
   pricePerTokenCSPR: number; // Price willing to pay per token
   tokenAmount: string; // Amount of tokens to buy
+  orderType?: OrderType;
+  expiresAt?: number; // Block time (ms) from which the order can no longer fill, 0 = never
+  routeToAmm?: boolean; // IOC only: buy any unfilled remainder on the bonding curve
   senderPublicKey: string;
 }): Promise<PlaceOrderResult> {
-  const { pricePerTokenCSPR, tokenAmount, senderPublicKey } = params;
+  const {
+    tokenContractHash,
+    pricePerTokenCSPR,
+    tokenAmount,
+    orderType = "limit",
+    expiresAt = 0,
+    routeToAmm = false,
+    senderPublicKey,
+  } = params;
 
   const status = await getOrderBookStatus();
   if (!status.configured || !status.contractHash) {
@@ -157,8 +184,12 @@ export async function placeBuyOrder(params: {
   const totalCostMotes = BigInt(priceMotes) * BigInt(tokenAmount);
 
   const runtimeArgs = RuntimeArgs.fromMap({
+    market: marketArg(tokenContractHash),
     price: CLValueBuilder.u512(priceMotes),
     amount: CLValueBuilder.u512(tokenAmount),
+    order_type: CLValueBuilder.u8(ORDER_TYPE_CODES[orderType]),
+    expires_at: CLValueBuilder.u64(expiresAt),
+    route_to_amm: CLValueBuilder.bool(routeToAmm),
   });
 
   // Payment includes gas + escrow for the order
@@ -228,11 +259,21 @@ export async function placeBuyOrder(params: {
  * Place a sell limit order
  */
 export async function placeSellOrder(params: {
+  tokenContractHash: string; // Market to trade in (token contract hash, "hash-" optional)
   pricePerTokenCSPR: number; // Price to sell per token
   tokenAmount: string; // Amount of tokens to sell
+  orderType?: OrderType;
+  expiresAt?: number; // Block time (ms) from which the order can no longer fill, 0 = never
   senderPublicKey: string;
 }): Promise<PlaceOrderResult> {
-  const { pricePerTokenCSPR, tokenAmount, senderPublicKey } = params;
+  const {
+    tokenContractHash,
+    pricePerTokenCSPR,
+    tokenAmount,
+    orderType = "limit",
+    expiresAt = 0,
+    senderPublicKey,
+  } = params;
 
   const status = await getOrderBookStatus();
   if (!status.configured || !status.contractHash) {
@@ -246,8 +287,11 @@ export async function placeSellOrder(params: {
   const priceMotes = Math.round(pricePerTokenCSPR * 1_000_000_000).toString();
 
   const runtimeArgs = RuntimeArgs.fromMap({
+    market: marketArg(tokenContractHash),
     price: CLValueBuilder.u512(priceMotes),
     amount: CLValueBuilder.u512(tokenAmount),
+    order_type: CLValueBuilder.u8(ORDER_TYPE_CODES[orderType]),
+    expires_at: CLValueBuilder.u64(expiresAt),
   });
 
   const deploy = DeployUtil.makeDeploy(
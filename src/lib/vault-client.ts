@@ -24,10 +24,13 @@ export interface VaultStatus {
 
 export interface OrderLock {
   orderId: string;
+  owner: string;
+  status: "open" | "partially_settled" | "settled" | "cancelled" | "expired";
   locked: {
     motes: string;
     cspr: number;
   };
+  expiresAt: number; // 0 = never
   vaultAccountHash: string;
 }
 
@@ -68,11 +71,17 @@ export async function getVaultStatus(): Promise<VaultStatus> {
 }
 
 /**
- * Get the locked CSPR amount for an order.
+ * Get the lock of an owner's order.
+ * Order IDs are only unique per owner, so the owner's account hash is required.
  * Returns null if order not found.
  */
-export async function getOrderLock(orderId: string): Promise<OrderLock | null> {
-  const response = await fetch(`/api/vault/orders/${encodeURIComponent(orderId)}`);
+export async function getOrderLock(
+  orderId: string,
+  ownerAccountHash: string
+): Promise<OrderLock | null> {
+  const response = await fetch(
+    `/api/vault/orders/${encodeURIComponent(orderId)}?owner=${encodeURIComponent(ownerAccountHash)}`
+  );
 
   if (response.status === 404) {
     return null;
@@ -88,8 +97,8 @@ export async function getOrderLock(orderId: string): Promise<OrderLock | null> {
 /**
  * Check if an order has CSPR locked in the vault.
  */
-export async function isOrderLocked(orderId: string): Promise<boolean> {
-  const lock = await getOrderLock(orderId);
+export async function isOrderLocked(orderId: string, ownerAccountHash: string): Promise<boolean> {
+  const lock = await getOrderLock(orderId, ownerAccountHash);
   return lock !== null && parseFloat(lock.locked.motes) > 0;
 }
 
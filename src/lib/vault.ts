@@ -9,7 +9,7 @@ import { appConfig } from "./config";
 // ============================================================================
 
 // Named keys in the vault contract account
-const KEY_LOCKED_CSPR = "locked_cspr";
+const KEY_LOCKS = "locks";
 const KEY_CSPR_PURSE = "cspr_purse";
 const KEY_ADMIN = "admin";
 const KEY_ORDER_BOOK = "order_book";
@@ -71,27 +71,73 @@ export function motesToCSPR(motes: number | string | bigint): number {
 // Query Functions
 // ============================================================================
 
+export type LockStatus = "open" | "partially_settled" | "settled" | "cancelled" | "expired";
+
+const LOCK_STATUSES: LockStatus[] = [
+  "open",
+  "partially_settled",
+  "settled",
+  "cancelled",
+  "expired",
+];
+
+export interface LockRecord {
+  originalMotes: string;
+  remainingMotes: string;
+  status: LockStatus;
+  createdAt: number;
+  updatedAt: number;
+  expiresAt: number; // 0 = never
+}
+
+function readU512(bytes: Buffer, offset: number): [bigint, number] {
+  const length = bytes[offset];
+  let value = BigInt(0);
+  for (let i = length - 1; i >= 0; i--) {
+    value = (value << BigInt(8)) | BigInt(bytes[offset + 1 + i]);
+  }
+  return [value, offset + 1 + length];
+}
+
 /**
- * Get the locked CSPR amount for an order.
- * Returns 0 if order doesn't exist.
+ * Decode a LockRecord as serialized by the vault:
+ * owner (32 bytes), original and remaining amounts (U512), status (u8),
+ * created_at, updated_at and expires_at (u64).
  */
-export async function getLockedAmount(orderId: string): Promise<{
-  amountMotes: string;
-  amountCSPR: number;
-}> {
+function parseLockRecord(hex: string): LockRecord {
+  const bytes = Buffer.from(hex, "hex");
+  let offset = 32;
+  const [original, afterOriginal] = readU512(bytes, offset);
+  const [remaining, afterRemaining] = readU512(bytes, afterOriginal);
+  offset = afterRemaining;
+  const status = LOCK_STATUSES[bytes[offset]];
+  offset += 1;
+  const createdAt = Number(bytes.readBigUInt64LE(offset));
+  const updatedAt = Number(bytes.readBigUInt64LE(offset + 8));
+  const expiresAt = Number(bytes.readBigUInt64LE(offset + 16));
+  return {
+    originalMotes: original.toString(),
+    remainingMotes: remaining.toString(),
+    status,
+    createdAt,
+    updatedAt,
+    expiresAt,
+  };
+}
+
+/**
+ * Get the lock record of an owner's order.
+ * Locks are keyed by "<owner account hash>_<order id>" in the `locks` dictionary.
+ * Returns null if the order doesn't exist.
+ */
+export async function getLockRecord(
+  ownerAccountHash: string,
+  orderId: string
+): Promise<LockRecord | null> {
   const vaultAccountHash = getVaultAccountHash();
+  const owner = ownerAccountHash.replace("account-hash-", "");
 
   try {
-    // Query the locked_cspr dictionary using direct RPC call (easier for dicts sometimes)
-    // Or use SDK v2 queryGlobalState
-    // Let's stick to the existing direct fetch pattern for dictionary items as it handles the path/key logic manually
-    // which is often more robust if SDK helpers change.
-
-    // BUT we should be consistent.
-    // SDK v2: service.stringGetDictionaryItem(stateRootHash, dictionaryKey, itemKey) ? 
-    // Actually direct fetch works fine and is detached from SDK version issues for basic queries.
-    // I will keep the fetch implementation but ensure it doesn't conflict.
-
     const rpcUrl = appConfig.rpcUrls.primary;
 
     // First get the dictionary seed URef
@@ -104,7 +150,7 @@ export async function getLockedAmount(orderId: string): Promise<{
         params: {
           state_identifier: null, // latest state
           key: vaultAccountHash,
-          path: [KEY_LOCKED_CSPR],
+          path: [KEY_LOCKS],
         },
         id: 1,
       }),
@@ -114,7 +160,7 @@ export async function getLockedAmount(orderId: string): Promise<{
 
     if (stateData.error) {
       console.log(`[Vault Query] Dictionary lookup failed:`, stateData.error);
-      return { amountMotes: "0", amountCSPR: 0 };
+      return null;
     }
 
     // Get the dictionary seed URef from result
@@ -122,7 +168,7 @@ export async function getLockedAmount(orderId: string): Promise<{
     const seedURef = storedValue?.CLValue?.parsed;
 
     if (!seedURef) {
-      return { amountMotes: "0", amountCSPR: 0 };
+      return null;
     }
 
     // Query dictionary item by key
@@ -137,7 +183,7 @@ export async function getLockedAmount(orderId: string): Promise<{
           dictionary_identifier: {
             URef: {
               seed_uref: seedURef,
-              dictionary_item_key: orderId,
+              dictionary_item_key: `${owner}_${orderId}`,
             },
           },
         },
@@ -149,31 +195,46 @@ export async function getLockedAmount(orderId: string): Promise<{
 
     if (dictData.error) {
       // Order not found in dictionary
-      return { amountMotes: "0", amountCSPR: 0 };
+      return null;
     }
 
-    const value = dictData.result?.stored_value?.CLValue?.parsed;
-    if (!value) {
-      return { amountMotes: "0", amountCSPR: 0 };
+    // Lock records are stored untyped, so decode the raw bytes
+    const bytes = dictData.result?.stored_value?.CLValue?.bytes;
+    if (!bytes) {
+      return null;
     }
 
-    const amountMotes = typeof value === "string" ? value : String(value);
-    return {
-      amountMotes,
-      amountCSPR: motesToCSPR(amountMotes),
-    };
+    return parseLockRecord(bytes);
   } catch (error) {
-    console.log(`[Vault Query] Order ${orderId} lookup error:`, error);
-    return { amountMotes: "0", amountCSPR: 0 };
+    console.log(`[Vault Query] Order ${owner}_${orderId} lookup error:`, error);
+    return null;
   }
 }
 
 /**
- * Check if an order exists in the vault.
+ * Get the CSPR still locked for an owner's order.
+ * Returns 0 if the order doesn't exist or is no longer active.
+ */
+export async function getLockedAmount(
+  ownerAccountHash: string,
+  orderId: string
+): Promise<{
+  amountMotes: string;
+  amountCSPR: number;
+}> {
+  const record = await getLockRecord(ownerAccountHash, orderId);
+  const amountMotes = record?.remainingMotes ?? "0";
+  return {
+    amountMotes,
+    amountCSPR: motesToCSPR(amountMotes),
+  };
+}
+
+/**
+ * Check if an owner's order exists in the vault, whatever its status.
  */
-export async function orderExists(orderId: string): Promise<boolean> {
-  const { amountMotes } = await getLockedAmount(orderId);
-  return amountMotes !== "0";
+export async function orderExists(ownerAccountHash: string, orderId: string): Promise<boolean> {
+  return (await getLockRecord(ownerAccountHash, orderId)) !== null;
 }
 
 /**